@@ -0,0 +1,5 @@
+#![no_main]
+
+use {edmv::fuzz::Scenario, libfuzzer_sys::fuzz_target};
+
+fuzz_target!(|data: &[u8]| Scenario::from_bytes(data).check());
@@ -0,0 +1,1075 @@
+use super::*;
+
+#[derive(Debug, Parser)]
+#[command(about, author, version, args_conflicts_with_subcommands = true)]
+pub(crate) struct Arguments {
+  #[clap(
+    long,
+    value_name = "COMMAND",
+    help = "Command to run once after renaming"
+  )]
+  after: Option<String>,
+  #[clap(
+    long,
+    conflicts_with = "stem",
+    help = "Only edit the final component of each path"
+  )]
+  basename: bool,
+  #[clap(
+    long,
+    value_name = "COMMAND",
+    help = "Command to run once before renaming"
+  )]
+  before: Option<String>,
+  #[clap(
+    long,
+    value_enum,
+    value_delimiter = ',',
+    conflicts_with = "tui",
+    help = "Read-only metadata columns to show before each path, in the dual format"
+  )]
+  columns: Vec<Column>,
+  #[clap(
+    long,
+    value_name = "EXTENSIONS",
+    value_delimiter = ',',
+    help = "Rename files sharing a renamed file's stem with one of EXTENSIONS, or any extension with `auto`"
+  )]
+  companions: Vec<String>,
+  #[clap(
+    long,
+    value_name = "FILE",
+    help = "Configuration file to read defaults from [default: $XDG_CONFIG_HOME/edmv/config.toml]"
+  )]
+  config: Option<PathBuf>,
+  #[clap(long, help = "Run without making any changes")]
+  dry_run: bool,
+  #[clap(long, help = "Editor command to use")]
+  editor: Option<String>,
+  #[clap(long = "type", value_enum, help = "Only list paths of this type")]
+  entry_type: Option<EntryType>,
+  #[clap(
+    long,
+    value_name = "GLOB",
+    value_parser = Pattern::new,
+    help = "Leave out paths with a component or path matching GLOB"
+  )]
+  exclude: Vec<Pattern>,
+  #[clap(
+    long,
+    value_name = "EXTENSIONS",
+    value_delimiter = ',',
+    help = "Only list files with one of EXTENSIONS"
+  )]
+  ext: Vec<String>,
+  #[clap(
+    long,
+    help = "Retarget symbolic links whose target would change after renaming"
+  )]
+  fix_symlinks: bool,
+  #[clap(long, help = "Overwrite existing files")]
+  force: bool,
+  #[clap(
+    long,
+    value_enum,
+    default_value_t = Format::Single,
+    help = "Buffer format to edit"
+  )]
+  format: Format,
+  #[clap(
+    long,
+    value_name = "COMMAND",
+    help = "Command to run after each rename"
+  )]
+  post_hook: Option<String>,
+  #[clap(
+    long,
+    value_name = "COMMAND",
+    help = "Command to run for each rename, before any rename is made"
+  )]
+  pre_hook: Option<String>,
+  #[clap(
+    long,
+    value_enum,
+    default_value_t = Policy::Abort,
+    requires = "pre_hook",
+    help = "Whether to abort the run or skip the rename when a pre-hook fails"
+  )]
+  pre_hook_failure: Policy,
+  #[clap(
+    long,
+    value_name = "NAME",
+    help = "Preset from the configuration file to take defaults from"
+  )]
+  preset: Option<String>,
+  #[clap(
+    long,
+    value_enum,
+    default_value_t = ProgressStyle::Auto,
+    help = "How to report progress on stderr once renaming takes over a second"
+  )]
+  progress: ProgressStyle,
+  #[clap(
+    long,
+    conflicts_with = "verbose",
+    help = "Don't print the number of paths changed"
+  )]
+  quiet: bool,
+  #[clap(long, help = "Resolve conflicting renames")]
+  resolve: bool,
+  #[clap(long, help = "Reverse the order of sources")]
+  reverse: bool,
+  #[clap(
+    long,
+    help = "Quote paths with leading or trailing whitespace in the buffer"
+  )]
+  show_whitespace: bool,
+  #[clap(
+    long,
+    value_enum,
+    default_value_t = Sort::None,
+    help = "Order in which to list sources, where `natural` reads ASCII digits as numbers and compares other characters by code point, ignoring case, rather than by locale"
+  )]
+  sort: Sort,
+  #[clap(name = "sources", help = "Paths to edit")]
+  sources: Vec<String>,
+  #[clap(
+    long,
+    help = "Only edit the stem of each path, keeping its extension"
+  )]
+  stem: bool,
+  #[command(subcommand)]
+  subcommand: Option<Subcommand>,
+  #[clap(
+    long,
+    value_name = "DIRECTORY",
+    requires = "fix_symlinks",
+    help = "Also retarget symbolic links found within DIRECTORY"
+  )]
+  symlink_root: Vec<PathBuf>,
+  #[clap(
+    long,
+    conflicts_with_all = [
+      "basename", "editor", "format", "show_whitespace", "stem"
+    ],
+    help = "Edit destinations in a built-in terminal UI instead of an editor"
+  )]
+  tui: bool,
+  #[clap(
+    long,
+    value_name = "GLOB",
+    help = "Rewrite references to renamed paths within files matching GLOB"
+  )]
+  update_references: Vec<String>,
+  #[clap(
+    short,
+    long,
+    action = ArgAction::Count,
+    help = "List each stage of renames, or with -vv, trace every check made"
+  )]
+  verbose: u8,
+  #[clap(
+    long,
+    help = "Wait for other runs on the same directories instead of failing"
+  )]
+  wait: bool,
+}
+
+impl Arguments {
+  fn buffer<'a>(&'a self, tree: Option<&'a Tree>) -> Buffer<'a> {
+    Buffer {
+      columns: &self.columns,
+      dry_run: self.dry_run,
+      force: self.force,
+      format: self.format,
+      resolve: self.resolve,
+      scope: self.scope(),
+      show_whitespace: self.show_whitespace,
+      sources: &self.sources,
+      tree,
+    }
+  }
+
+  /// Refuses, or waits with `--wait`, while another run holds a directory
+  /// beneath one of the sources, which renaming the source would move.
+  fn check_subtrees(&self, locks: &BTreeMap<PathBuf, Option<Lock>>) -> Result {
+    let mut directories = Vec::new();
+
+    for source in &self.sources {
+      let source = Path::new(source).absolutize()?;
+
+      if fs::symlink_metadata(&source).is_ok_and(|metadata| metadata.is_dir()) {
+        directories.push(source.into_owned());
+      }
+    }
+
+    while let Some(directory) = directories.pop() {
+      if !locks.contains_key(&directory) {
+        Lock::check(&directory, self.wait)?;
+      }
+
+      let Ok(entries) = fs::read_dir(&directory) else {
+        continue;
+      };
+
+      for entry in entries.flatten() {
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+          directories.push(entry.path());
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  fn documents(
+    &self,
+    plan: &Plan,
+    filesystem: &dyn Filesystem,
+  ) -> Result<Vec<Document>> {
+    if self.update_references.is_empty() {
+      return Ok(Vec::new());
+    }
+
+    let moves = Self::moves(plan, filesystem)?;
+
+    let mut paths = BTreeSet::new();
+
+    for pattern in &self.update_references {
+      let entries = glob(pattern).map_err(|source| Error::Pattern {
+        pattern: pattern.clone(),
+        source,
+      })?;
+
+      for entry in entries {
+        let path = entry.map_err(io::Error::from)?;
+
+        if filesystem.is_file(&path) {
+          paths.insert(path);
+        }
+      }
+    }
+
+    paths
+      .into_iter()
+      .filter_map(|path| Document::scan(path, &moves, filesystem).transpose())
+      .collect()
+  }
+
+  fn edit(&self, buffer: &Buffer) -> Result<String> {
+    let editor = self.editor.clone().unwrap_or(
+      env::var("EDMV_EDITOR")
+        .unwrap_or(env::var("EDITOR").unwrap_or("vi".to_string())),
+    );
+
+    let mut file = Builder::new()
+      .prefix(&format!("{}-", env!("CARGO_PKG_NAME")))
+      .suffix(".txt")
+      .tempfile()?;
+
+    write!(file, "{buffer}")?;
+
+    let status = Command::new(&editor)
+      .arg(file.path())
+      .status()
+      .map_err(|source| Error::EditorLaunch { editor, source })?;
+
+    if !status.success() {
+      return Err(Error::EditorStatus);
+    }
+
+    Ok(fs::read_to_string(file.path())?)
+  }
+
+  fn filter(&self) -> Filter {
+    Filter {
+      entry_type: self.entry_type,
+      excludes: &self.exclude,
+      extensions: &self.ext,
+    }
+  }
+
+  fn links(
+    &self,
+    plan: &Plan,
+    filesystem: &dyn Filesystem,
+  ) -> Result<Vec<Link>> {
+    if !self.fix_symlinks {
+      return Ok(Vec::new());
+    }
+
+    let moves = Self::moves(plan, filesystem)?;
+
+    let mut paths = BTreeSet::new();
+
+    for (source, _) in plan.pairs() {
+      Link::find(source, &mut paths)?;
+    }
+
+    for root in &self.symlink_root {
+      Link::find(root, &mut paths)?;
+    }
+
+    paths
+      .into_iter()
+      .filter_map(|path| Link::scan(path, &moves).transpose())
+      .collect()
+  }
+
+  /// Parses the command line, filling in flags it doesn't give from the
+  /// configuration file and the selected preset.
+  pub(crate) fn load() -> Result<Self> {
+    let arguments = env::args_os().collect::<Vec<OsString>>();
+
+    let command = Self::command();
+
+    let matches = command.clone().get_matches_from(&arguments);
+
+    let given =
+      Self::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+
+    if given.subcommand.is_some() {
+      return Ok(given);
+    }
+
+    let config = match given.config {
+      Some(path) => Config::load(path, true)?,
+      None => Config::load(Config::default_path().unwrap_or_default(), false)?,
+    };
+
+    let defaults =
+      config.arguments(&command, given.preset.as_deref(), |arg| {
+        matches.value_source(arg.get_id().as_str())
+          == Some(ValueSource::CommandLine)
+      })?;
+
+    let matches = command.get_matches_from(
+      arguments
+        .iter()
+        .take(1)
+        .chain(&defaults)
+        .chain(arguments.iter().skip(1)),
+    );
+
+    let mut arguments =
+      Self::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+
+    arguments.reconcile(&matches);
+
+    Ok(arguments)
+  }
+
+  /// Locks the parent directory of each of `paths`, and each of them that is
+  /// a directory, since renaming it moves everything beneath it, unless it is
+  /// already in `locks`. Also refuses directories above those which another
+  /// run holds, since a run renaming their entries may move these along with
+  /// them. Only the first directories locked may wait for another run, since
+  /// waiting while holding locks could deadlock with a run doing the same.
+  fn lock<'a>(
+    &self,
+    paths: impl Iterator<Item = &'a Path>,
+    locks: &mut BTreeMap<PathBuf, Option<Lock>>,
+  ) -> Result {
+    let wait = self.wait && locks.is_empty();
+
+    let mut directories = BTreeSet::new();
+
+    for path in paths {
+      let path = path.absolutize()?;
+
+      if let Some(parent) = path.parent() {
+        directories.insert(parent.to_owned());
+      }
+
+      if fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.is_dir()) {
+        directories.insert(path.into_owned());
+      }
+    }
+
+    let ancestors = directories
+      .iter()
+      .flat_map(|directory| directory.ancestors().skip(1))
+      .filter(|ancestor| {
+        !directories.contains(*ancestor) && !locks.contains_key(*ancestor)
+      })
+      .map(Path::to_owned)
+      .collect::<BTreeSet<PathBuf>>();
+
+    for ancestor in ancestors {
+      Lock::check(&ancestor, wait)?;
+    }
+
+    for directory in directories {
+      if let btree_map::Entry::Vacant(entry) = locks.entry(directory) {
+        let lock = Lock::acquire(entry.key(), wait)?;
+        entry.insert(lock);
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Points the locks on `from` and the directories beneath it at where they
+  /// are once `from` has been renamed to `to`.
+  fn moved(
+    locks: &mut BTreeMap<PathBuf, Option<Lock>>,
+    from: &Path,
+    to: &Path,
+  ) {
+    let (Ok(from), Ok(to)) = (from.absolutize(), to.absolutize()) else {
+      return;
+    };
+
+    for lock in locks
+      .range_mut(from.to_path_buf()..)
+      .take_while(|(directory, _)| directory.starts_with(&from))
+      .filter_map(|(_, lock)| lock.as_mut())
+    {
+      lock.moved(&from, &to);
+    }
+  }
+
+  fn moves(
+    plan: &Plan,
+    filesystem: &dyn Filesystem,
+  ) -> Result<Vec<(PathBuf, PathBuf)>> {
+    plan
+      .pairs()
+      .iter()
+      .zip(plan.targets(filesystem))
+      .map(|((source, _), target)| {
+        Ok((
+          source.absolutize()?.into_owned(),
+          target.absolutize()?.into_owned(),
+        ))
+      })
+      .collect()
+  }
+
+  fn pairs(
+    &self,
+    filesystem: &dyn Filesystem,
+  ) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let destinations = if self.tui {
+      Tui::new(filesystem, self.force, self.resolve, &self.sources)?.run()?
+    } else {
+      let tabbed = self
+        .sources
+        .iter()
+        .filter(|source| source.contains('\t'))
+        .cloned()
+        .collect::<Vec<String>>();
+
+      if self.format == Format::Dual && !tabbed.is_empty() {
+        return Err(Error::TabbedPaths { paths: tabbed });
+      }
+
+      let buffer = self.buffer(None);
+
+      buffer.parse(&self.edit(&buffer)?)?
+    };
+
+    if !self.sources.is_empty() && destinations.is_empty() {
+      return Err(Error::Aborted);
+    }
+
+    if self.sources.len() != destinations.len() {
+      return Err(Error::CountMismatch {
+        expected: self.sources.len(),
+        received: destinations.len(),
+      });
+    }
+
+    let empty = self
+      .sources
+      .iter()
+      .zip(destinations.iter())
+      .filter(|(_, destination)| destination.is_empty())
+      .map(|(source, _)| source.clone())
+      .collect::<Vec<String>>();
+
+    if !empty.is_empty() {
+      return Err(Error::EmptyDestinations { sources: empty });
+    }
+
+    let scope = self.scope();
+
+    Ok(
+      self
+        .sources
+        .iter()
+        .zip(destinations.iter())
+        .map(|(source, destination)| (source, scope.join(source, destination)))
+        .filter(|(source, destination)| {
+          let unchanged = *source == destination;
+
+          if unchanged {
+            self.trace(
+              2,
+              format_args!("check: dropped `{source}`, it is unchanged"),
+            );
+          }
+
+          !unchanged
+        })
+        .map(|(source, destination)| {
+          (PathBuf::from(source), PathBuf::from(destination))
+        })
+        .collect(),
+    )
+  }
+
+  /// Runs the pre-hook for every pair, dropping and revalidating those whose
+  /// hook failed under `--pre-hook-failure skip`.
+  fn pre_hooks(&self, plan: Plan, filesystem: &dyn Filesystem) -> Result<Plan> {
+    let Some(command) = &self.pre_hook else {
+      return Ok(plan);
+    };
+
+    let (mut pairs, mut skipped) = (Vec::new(), HashSet::new());
+
+    for (source, destination) in plan.pairs() {
+      match Hook(command).pair(source, destination) {
+        Ok(()) => pairs.push((source.clone(), destination.clone())),
+        Err(error) if self.pre_hook_failure == Policy::Skip => {
+          eprintln!(
+            "warning: {error}, skipping {} -> {}",
+            source.display(),
+            destination.display()
+          );
+
+          skipped.insert(source);
+        }
+        Err(error) => return Err(error),
+      }
+    }
+
+    if skipped.is_empty() {
+      return Ok(plan);
+    }
+
+    let mut clobbered = pairs
+      .iter()
+      .filter(|(_, destination)| skipped.contains(destination))
+      .map(|(_, destination)| destination.display().to_string())
+      .collect::<Vec<String>>();
+
+    if !clobbered.is_empty() {
+      clobbered.sort();
+
+      return Err(Error::SkippedDestinations {
+        destinations: clobbered,
+      });
+    }
+
+    let plan = Plan::new(pairs).with_creations(plan.creations().to_vec());
+
+    plan.validate(filesystem, self.force, self.resolve)?;
+
+    Ok(plan)
+  }
+
+  /// Checks flags that conflict only with certain values of another flag,
+  /// which clap can't express on its own. With `--columns`, lines may be
+  /// sorted in the editor, so the single format, which matches lines to
+  /// sources by position, is replaced by the dual format unless it was asked
+  /// for.
+  fn reconcile(&mut self, matches: &ArgMatches) {
+    if self.format == Format::Tree
+      && (self.basename
+        || self.stem
+        || self.show_whitespace
+        || !self.columns.is_empty())
+    {
+      Self::command()
+        .error(
+          ErrorKind::ArgumentConflict,
+          "--format tree cannot be used with --basename, --columns, --show-whitespace, or --stem",
+        )
+        .exit();
+    }
+
+    if self.format == Format::Single && !self.columns.is_empty() {
+      if matches.value_source("format") == Some(ValueSource::DefaultValue) {
+        self.format = Format::Dual;
+      } else {
+        Self::command()
+          .error(
+            ErrorKind::ArgumentConflict,
+            "--format single cannot be used with --columns, since sorting lines would reorder renames",
+          )
+          .exit();
+      }
+    }
+  }
+
+  /// The `-v` line for a single rename call from `from` to `to`.
+  fn rename_trace(from: &Path, to: &Path) -> String {
+    format!("  `{}` -> `{}`", from.display(), to.display())
+  }
+
+  fn report(documents: &[Document]) -> usize {
+    let mut count = 0;
+
+    for document in documents {
+      for reference in document.references() {
+        println!(
+          "{}:{}: {} -> {}",
+          document.path().display(),
+          reference.line,
+          reference.original,
+          reference.replacement
+        );
+
+        count += 1;
+      }
+    }
+
+    count
+  }
+
+  fn report_links(links: &[Link]) -> usize {
+    for link in links {
+      println!(
+        "{}: {} -> {}",
+        link.path().display(),
+        link.target().display(),
+        link.replacement().display()
+      );
+    }
+
+    links.len()
+  }
+
+  pub(crate) fn run(mut self) -> Result {
+    if let Some(subcommand) = self.subcommand.take() {
+      return subcommand.run();
+    }
+
+    if !self.dry_run {
+      Journal::check(
+        &self
+          .sources
+          .iter()
+          .map(|source| Ok(Path::new(source).absolutize()?.into_owned()))
+          .collect::<Result<BTreeSet<PathBuf>>>()?,
+      )?;
+    }
+
+    let mut filesystem = Real::default();
+
+    let absent = self
+      .sources
+      .clone()
+      .into_iter()
+      .filter(|path| !filesystem.exists(Path::new(path)))
+      .collect::<Vec<String>>();
+
+    if !absent.is_empty() {
+      return Err(Error::NonExistent { paths: absent });
+    }
+
+    let filter = self.filter();
+
+    let sources = self
+      .sources
+      .iter()
+      .filter(|source| {
+        let path = Path::new(source);
+
+        let kind = if filesystem.is_dir(path) {
+          Kind::Directory
+        } else {
+          Kind::File
+        };
+
+        let included = !filter.excludes(path) && filter.includes(path, kind);
+
+        if !included {
+          self.trace(
+            2,
+            format_args!("check: skipped `{source}`, it is filtered out"),
+          );
+        }
+
+        included
+      })
+      .cloned()
+      .collect();
+
+    self.sources = sources;
+
+    self.sort.sort(&mut self.sources, self.reverse);
+
+    let mut locks = BTreeMap::new();
+
+    if !self.dry_run {
+      self.lock(self.sources.iter().map(Path::new), &mut locks)?;
+      self.check_subtrees(&locks)?;
+    }
+
+    let (mut pairs, creations) = if self.format == Format::Tree {
+      self.tree()?
+    } else {
+      (self.pairs(&filesystem)?, Vec::new())
+    };
+
+    if let Some(companions) = Companions::new(&self.companions) {
+      let filter = self.filter();
+
+      let extra = companions
+        .pairs(&pairs, &filesystem)?
+        .into_iter()
+        .filter(|(source, _)| {
+          !filter.excludes(source) && filter.includes(source, Kind::File)
+        })
+        .collect::<Vec<(PathBuf, PathBuf)>>();
+
+      for (source, destination) in &extra {
+        self.trace(
+          2,
+          format_args!(
+            "check: added companion `{}` -> `{}`",
+            source.display(),
+            destination.display()
+          ),
+        );
+      }
+
+      pairs.extend(extra);
+    }
+
+    if !self.dry_run {
+      self.lock(
+        pairs
+          .iter()
+          .flat_map(|(source, destination)| [source, destination])
+          .chain(creations.iter().map(|(path, _)| path))
+          .map(PathBuf::as_path),
+        &mut locks,
+      )?;
+    }
+
+    let plan = Plan::new(pairs).with_creations(creations);
+
+    if self.verbose >= 2 {
+      self.trace_checks(&plan, &filesystem)?;
+    }
+
+    plan.validate(&filesystem, self.force, self.resolve)?;
+
+    if self.dry_run {
+      let mut summary = Vec::new();
+
+      plan.execute(
+        &mut Memory::overlay(&filesystem),
+        self.resolve,
+        |event| match event {
+          Event::Create { .. } => {}
+          Event::Created { kind, path } => {
+            println!(
+              "{}(\"{}\")",
+              match kind {
+                Kind::Directory => "create_dir",
+                Kind::File => "create_file",
+              },
+              path.display()
+            );
+
+            summary.push(format!("created {}", path.display()));
+          }
+          Event::Rename { from, to } => {
+            self.trace(1, Self::rename_trace(from, to));
+            println!("rename(\"{}\", \"{}\")", from.display(), to.display());
+          }
+          Event::Renamed {
+            destination,
+            source,
+          } => {
+            summary.push(format!(
+              "{} -> {}",
+              source.display(),
+              destination.display()
+            ));
+          }
+          Event::Stage { index, stages } => {
+            self.trace(1, Self::stage_trace(index, stages));
+          }
+        },
+      )?;
+
+      for line in summary {
+        println!("{line}");
+      }
+
+      let documents = self.documents(&plan, &filesystem)?;
+
+      Self::report(&documents);
+
+      let links = self.links(&plan, &filesystem)?;
+
+      Self::report_links(&links);
+
+      self.summarize(0, 0, 0);
+
+      return Ok(());
+    }
+
+    if let Some(command) = &self.before {
+      Hook(command).batch(plan.pairs().len())?;
+    }
+
+    let plan = self.pre_hooks(plan, &filesystem)?;
+
+    let documents = self.documents(&plan, &filesystem)?;
+
+    let links = self.links(&plan, &filesystem)?;
+
+    let stages = plan.stages(&mut filesystem, self.resolve)?;
+
+    let mut journal =
+      Journal::write(&Self::steps(&plan, &stages, &documents, &links)?)?;
+
+    if journal.is_some() {
+      filesystem.keep()?;
+    }
+
+    let mut failed = 0;
+
+    let mut progress = Progress::new(
+      self.progress,
+      plan.creations().len() + stages.iter().map(Vec::len).sum::<usize>(),
+    );
+
+    let changed = plan.perform(&mut filesystem, &stages, |event| {
+      if let (Event::Create { .. } | Event::Rename { .. }, Some(journal)) =
+        (event, &mut journal)
+      {
+        journal.begin();
+      }
+
+      progress.observe(&event);
+
+      match event {
+        Event::Created { path, .. } => {
+          progress.println(format!("created {}", path.display()));
+        }
+        Event::Rename { from, to } if self.verbose > 0 => {
+          progress.eprintln(Self::rename_trace(from, to));
+        }
+        Event::Renamed {
+          destination,
+          source,
+        } => {
+          progress.println(format!(
+            "{} -> {}",
+            source.display(),
+            destination.display()
+          ));
+
+          Self::moved(&mut locks, source, destination);
+
+          if let Some(command) = &self.post_hook {
+            if let Err(error) = Hook(command).pair(source, destination) {
+              progress.eprintln(format!("warning: {error}"));
+              failed += 1;
+            }
+          }
+        }
+        Event::Stage { index, stages } if self.verbose > 0 => {
+          progress.eprintln(Self::stage_trace(index, stages));
+        }
+        Event::Create { .. } | Event::Rename { .. } | Event::Stage { .. } => {}
+      }
+    });
+
+    progress.finish();
+
+    let changed =
+      changed.map_err(|error| Journal::interrupt(journal.as_ref(), error))?;
+
+    for document in &documents {
+      if let Some(journal) = &mut journal {
+        journal.begin();
+      }
+
+      document
+        .apply()
+        .map_err(|error| Journal::interrupt(journal.as_ref(), error))?;
+    }
+
+    let updated = Self::report(&documents);
+
+    for link in &links {
+      if let Some(journal) = &mut journal {
+        journal.begin();
+      }
+
+      link
+        .apply()
+        .map_err(|error| Journal::interrupt(journal.as_ref(), error))?;
+    }
+
+    let retargeted = Self::report_links(&links);
+
+    if let Some(journal) = journal {
+      journal.finish()?;
+    }
+
+    self.summarize(changed, updated, retargeted);
+
+    if let Some(command) = &self.after {
+      Hook(command).batch(changed)?;
+    }
+
+    if failed > 0 {
+      return Err(Error::PostHooks { failed });
+    }
+
+    Ok(())
+  }
+
+  fn scope(&self) -> Scope {
+    if self.basename {
+      Scope::Basename
+    } else if self.stem {
+      Scope::Stem
+    } else {
+      Scope::Path
+    }
+  }
+
+  /// The `-v` line announcing stage `index` of `stages`.
+  fn stage_trace(index: usize, stages: usize) -> String {
+    format!("stage {} of {stages}:", index + 1)
+  }
+
+  /// Returns every step of a real run, in the order it makes them, for its
+  /// journal.
+  fn steps(
+    plan: &Plan,
+    stages: &[Vec<(PathBuf, PathBuf)>],
+    documents: &[Document],
+    links: &[Link],
+  ) -> Result<Vec<Step>> {
+    let absolute =
+      |path: &Path| -> Result<PathBuf> { Ok(path.absolutize()?.into_owned()) };
+
+    let mut steps = Vec::new();
+
+    for (path, kind) in plan.creations() {
+      steps.push(Step::Create {
+        kind: *kind,
+        path: absolute(path)?,
+      });
+    }
+
+    for (from, to) in stages.iter().flatten() {
+      steps.push(Step::Rename {
+        from: absolute(from)?,
+        to: absolute(to)?,
+      });
+    }
+
+    for document in documents {
+      steps.push(Step::Document {
+        content: document.content().to_owned(),
+        destination: absolute(document.destination())?,
+        path: absolute(document.path())?,
+        rewritten: document.rewritten(),
+      });
+    }
+
+    for link in links {
+      steps.push(Step::Link {
+        destination: absolute(link.destination())?,
+        path: absolute(link.path())?,
+        replacement: link.replacement().to_owned(),
+        target: link.target().to_owned(),
+      });
+    }
+
+    Ok(steps)
+  }
+
+  fn summarize(&self, changed: usize, updated: usize, retargeted: usize) {
+    if self.quiet {
+      return;
+    }
+
+    println!("{changed} path(s) changed");
+
+    if !self.update_references.is_empty() {
+      println!("{updated} reference(s) updated");
+    }
+
+    if self.fix_symlinks {
+      println!("{retargeted} link(s) retargeted");
+    }
+  }
+
+  fn trace(&self, level: u8, message: impl Display) {
+    if self.verbose >= level {
+      eprintln!("{message}");
+    }
+  }
+
+  fn trace_checks(&self, plan: &Plan, filesystem: &dyn Filesystem) -> Result {
+    let allowed = |problem: Problem| match problem {
+      Problem::Conflicting if self.resolve => " (allowed by --resolve)",
+      Problem::Existing if self.force => " (allowed by --force)",
+      _ => "",
+    };
+
+    let describe = |problems: &[Problem]| {
+      if problems.is_empty() {
+        return "ok".to_owned();
+      }
+
+      problems
+        .iter()
+        .map(|problem| format!("{problem}{}", allowed(*problem)))
+        .collect::<Vec<String>>()
+        .join(", ")
+    };
+
+    for ((source, destination), problems) in plan
+      .pairs()
+      .iter()
+      .zip(plan.problems(filesystem, false, false)?)
+    {
+      self.trace(
+        2,
+        format_args!(
+          "check: `{}` -> `{}`: {}",
+          source.display(),
+          destination.display(),
+          describe(&problems)
+        ),
+      );
+    }
+
+    for ((path, _), problems) in plan
+      .creations()
+      .iter()
+      .zip(plan.creation_problems(filesystem)?)
+    {
+      self.trace(
+        2,
+        format_args!(
+          "check: create `{}`: {}",
+          path.display(),
+          describe(&problems)
+        ),
+      );
+    }
+
+    Ok(())
+  }
+
+  fn tree(&self) -> Result<(Vec<(PathBuf, PathBuf)>, Vec<(PathBuf, Kind)>)> {
+    let tree = Tree::new(&self.sources, &self.filter())?;
+
+    tree.parse(&self.edit(&self.buffer(Some(&tree)))?, self.resolve)
+  }
+}
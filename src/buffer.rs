@@ -0,0 +1,297 @@
+use super::*;
+
+const COLUMNS: &str = " | ";
+const COMMENT: char = '#';
+const ESCAPE: char = '\\';
+const QUOTE: char = '"';
+const SEPARATOR: char = '\t';
+
+#[derive(Debug)]
+pub(crate) struct Buffer<'a> {
+  pub(crate) columns: &'a [Column],
+  pub(crate) dry_run: bool,
+  pub(crate) force: bool,
+  pub(crate) format: Format,
+  pub(crate) resolve: bool,
+  pub(crate) scope: Scope,
+  pub(crate) show_whitespace: bool,
+  pub(crate) sources: &'a [String],
+  pub(crate) tree: Option<&'a Tree>,
+}
+
+impl Display for Buffer<'_> {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    let flag = |enabled: bool| if enabled { "on" } else { "off" };
+
+    match self.format {
+      Format::Dual => {
+        writeln!(
+          f,
+          "# Edit the destination column below to rename paths. Each line holds"
+        )?;
+        writeln!(
+          f,
+          "# a source and its destination separated by a tab. Lines may be"
+        )?;
+        writeln!(f, "# reordered, but the source column must not change.")?;
+      }
+      Format::Single => writeln!(
+        f,
+        "# Edit the paths below to rename them, one path per line."
+      )?,
+      Format::Tree => {
+        writeln!(
+          f,
+          "# Edit the names below to rename paths. Each line starts with an ID"
+        )?;
+        writeln!(
+          f,
+          "# that must be kept, and indentation places a path inside the"
+        )?;
+        writeln!(
+          f,
+          "# directory above it. Renaming a directory renames its contents."
+        )?;
+        writeln!(
+          f,
+          "# Lines without an ID create an empty file, or a directory if they"
+        )?;
+        writeln!(f, "# end with `/`.")?;
+      }
+    }
+    writeln!(f, "#")?;
+    writeln!(
+      f,
+      "# Lines starting with `{COMMENT}` are ignored. To name a path that starts"
+    )?;
+    writeln!(
+      f,
+      "# with `{COMMENT}` or `{ESCAPE}`, prefix it with `{ESCAPE}`."
+    )?;
+    match self.scope {
+      Scope::Basename => writeln!(
+        f,
+        "# Only the final component of each path is shown, and may not contain `/`."
+      )?,
+      Scope::Path => {}
+      Scope::Stem => writeln!(
+        f,
+        "# Only the stem of each path is shown, and its extension is kept."
+      )?,
+    }
+    if !self.columns.is_empty() {
+      writeln!(
+        f,
+        "# The columns before `|` on each line are read-only and ignored."
+      )?;
+    }
+    if self.show_whitespace {
+      writeln!(
+        f,
+        "# Paths wrapped in `{QUOTE}` keep their leading and trailing whitespace."
+      )?;
+    }
+    writeln!(f, "#")?;
+    writeln!(
+      f,
+      "# --force: {}, --resolve: {}, --dry-run: {}",
+      flag(self.force),
+      flag(self.resolve),
+      flag(self.dry_run)
+    )?;
+    writeln!(f, "#")?;
+    writeln!(
+      f,
+      "# To abort, delete every path or exit the editor with a non-zero status."
+    )?;
+
+    if let Some(tree) = self.tree {
+      return write!(f, "{tree}");
+    }
+
+    let table = self
+      .sources
+      .iter()
+      .map(|source| {
+        self
+          .columns
+          .iter()
+          .map(|column| column.render(Path::new(source)))
+          .collect::<Vec<String>>()
+      })
+      .collect::<Vec<Vec<String>>>();
+
+    let widths = (0..self.columns.len())
+      .map(|i| table.iter().map(|row| row[i].len()).max().unwrap_or(0))
+      .collect::<Vec<usize>>();
+
+    for (source, row) in self.sources.iter().zip(&table) {
+      if !row.is_empty() {
+        let cells = self
+          .columns
+          .iter()
+          .zip(row)
+          .zip(&widths)
+          .map(|((column, cell), &width)| {
+            if column.right() {
+              format!("{cell:>width$}")
+            } else {
+              format!("{cell:<width$}")
+            }
+          })
+          .collect::<Vec<String>>();
+
+        write!(f, "{}{COLUMNS}", cells.join("  "))?;
+      }
+
+      match self.format {
+        Format::Dual => writeln!(
+          f,
+          "{}{SEPARATOR}{}",
+          self.escape(source),
+          self.escape(&self.scope.name(source))
+        )?,
+        Format::Single | Format::Tree => {
+          writeln!(f, "{}", self.escape(&self.scope.name(source)))?;
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl Buffer<'_> {
+  fn escape(&self, path: &str) -> String {
+    if self.show_whitespace && path.trim() != path {
+      format!("{QUOTE}{path}{QUOTE}")
+    } else if path.starts_with([COMMENT, ESCAPE])
+      || (self.show_whitespace && path.starts_with(QUOTE))
+    {
+      format!("{ESCAPE}{path}")
+    } else {
+      path.to_string()
+    }
+  }
+
+  pub(crate) fn parse(&self, content: &str) -> Result<Vec<String>> {
+    let lines = content
+      .lines()
+      .filter(|line| !line.starts_with(COMMENT))
+      .map(|line| {
+        if self.columns.is_empty() {
+          line
+        } else {
+          line.split_once(COLUMNS).map_or(line, |(_, rest)| rest)
+        }
+      })
+      .collect::<Vec<&str>>();
+
+    let destinations = match self.format {
+      Format::Dual => self.parse_dual(&lines)?,
+      Format::Single | Format::Tree => {
+        lines.iter().map(|line| self.unescape(line)).collect()
+      }
+    };
+
+    if self.scope != Scope::Path {
+      let separated = destinations
+        .iter()
+        .filter(|destination| destination.contains(is_separator))
+        .cloned()
+        .collect::<Vec<String>>();
+
+      if !separated.is_empty() {
+        return Err(Error::SeparatedNames { names: separated });
+      }
+    }
+
+    Ok(destinations)
+  }
+
+  fn parse_dual(&self, lines: &[&str]) -> Result<Vec<String>> {
+    if lines.is_empty() {
+      return Ok(Vec::new());
+    }
+
+    let unseparated = lines
+      .iter()
+      .filter(|line| !line.contains(SEPARATOR))
+      .map(|line| self.unescape(line))
+      .collect::<Vec<String>>();
+
+    if !unseparated.is_empty() {
+      return Err(Error::UnseparatedLines { lines: unseparated });
+    }
+
+    let pairs = lines
+      .iter()
+      .filter_map(|line| line.split_once(SEPARATOR))
+      .map(|(source, destination)| {
+        (self.unescape(source), self.unescape(destination))
+      })
+      .collect::<Vec<(String, String)>>();
+
+    let unknown = pairs
+      .iter()
+      .filter(|(source, _)| !self.sources.contains(source))
+      .map(|(source, _)| source.clone())
+      .collect::<Vec<String>>();
+
+    if !unknown.is_empty() {
+      return Err(Error::ChangedSources { sources: unknown });
+    }
+
+    let mut destinations = HashMap::new();
+
+    let mut repeated = Vec::new();
+
+    for (source, destination) in &pairs {
+      if destinations.insert(source, destination).is_some()
+        && !repeated.contains(source)
+      {
+        repeated.push(source.clone());
+      }
+    }
+
+    if !repeated.is_empty() {
+      return Err(Error::RepeatedSources { sources: repeated });
+    }
+
+    let missing = self
+      .sources
+      .iter()
+      .filter(|source| !destinations.contains_key(source))
+      .cloned()
+      .collect::<Vec<String>>();
+
+    if !missing.is_empty() {
+      return Err(Error::MissingSources { sources: missing });
+    }
+
+    Ok(
+      self
+        .sources
+        .iter()
+        .map(|source| destinations[source].clone())
+        .collect(),
+    )
+  }
+
+  fn unescape(&self, line: &str) -> String {
+    if let Some(path) = line.strip_prefix(ESCAPE) {
+      return path.to_string();
+    }
+
+    if self.show_whitespace {
+      if let Some(path) = line
+        .strip_prefix(QUOTE)
+        .and_then(|line| line.strip_suffix(QUOTE))
+      {
+        return path.to_string();
+      }
+    }
+
+    line.to_string()
+  }
+}
@@ -0,0 +1,109 @@
+use super::*;
+
+/// A read-only column of metadata shown before each path in the buffer.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub(crate) enum Column {
+  Mode,
+  Mtime,
+  Size,
+  Type,
+}
+
+impl Column {
+  /// Formats a timestamp as `YYYY-MM-DD HH:MM:SS` in UTC, so that columns
+  /// sort chronologically as text.
+  fn date(time: SystemTime) -> String {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+      Ok(duration) => i64::try_from(duration.as_secs()).unwrap_or(i64::MAX),
+      Err(error) => {
+        -i64::try_from(error.duration().as_secs()).unwrap_or(i64::MAX)
+      }
+    };
+
+    let (days, seconds) =
+      (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+
+    let era = (days + 719_468).div_euclid(146_097);
+    let day_of_era = (days + 719_468).rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+      - day_of_era / 146_096)
+      / 365;
+    let day_of_year =
+      day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+      shifted_month + 3
+    } else {
+      shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+      "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+      seconds / 3600,
+      seconds / 60 % 60,
+      seconds % 60
+    )
+  }
+
+  fn mode(metadata: &fs::Metadata) -> String {
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+
+      let mode = metadata.permissions().mode();
+
+      "rwxrwxrwx"
+        .chars()
+        .enumerate()
+        .map(|(i, c)| if mode & (0o400 >> i) == 0 { '-' } else { c })
+        .collect()
+    }
+
+    #[cfg(not(unix))]
+    {
+      if metadata.permissions().readonly() {
+        "r-".into()
+      } else {
+        "rw".into()
+      }
+    }
+  }
+
+  /// Renders this column for the path `path`, without following a final
+  /// symbolic link.
+  pub(crate) fn render(self, path: &Path) -> String {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+      return "?".into();
+    };
+
+    match self {
+      Self::Mode => Self::mode(&metadata),
+      Self::Mtime => {
+        metadata.modified().map_or_else(|_| "?".into(), Self::date)
+      }
+      Self::Size => metadata.len().to_string(),
+      Self::Type => {
+        let file_type = metadata.file_type();
+
+        if file_type.is_symlink() {
+          "link"
+        } else if file_type.is_dir() {
+          "dir"
+        } else if file_type.is_file() {
+          "file"
+        } else {
+          "other"
+        }
+        .into()
+      }
+    }
+  }
+
+  /// Whether values are aligned to the right, so that they line up by
+  /// magnitude.
+  pub(crate) fn right(self) -> bool {
+    self == Self::Size
+  }
+}
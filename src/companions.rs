@@ -0,0 +1,126 @@
+use super::*;
+
+/// Files renamed along with a primary file whose stem they share, such as
+/// `IMG_001.xmp` or `IMG_001.CR2.xmp` alongside `IMG_001.CR2`.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Companions {
+  Any,
+  Extensions(Vec<String>),
+}
+
+impl Companions {
+  fn matches(&self, suffix: &str) -> bool {
+    match self {
+      Self::Any => true,
+      Self::Extensions(extensions) => {
+        let extension = suffix.rsplit('.').next().unwrap_or(suffix);
+
+        extensions
+          .iter()
+          .any(|candidate| candidate.eq_ignore_ascii_case(extension))
+      }
+    }
+  }
+
+  pub(crate) fn new(extensions: &[String]) -> Option<Self> {
+    match extensions {
+      [] => None,
+      [extension] if extension == "auto" => Some(Self::Any),
+      extensions => Some(Self::Extensions(
+        extensions
+          .iter()
+          .map(|extension| extension.trim_start_matches('.').to_owned())
+          .collect(),
+      )),
+    }
+  }
+
+  /// Returns a rename for each companion of a renamed file in `pairs`,
+  /// giving it the stem the file ends up with.
+  ///
+  /// Companions which are already being renamed are left alone.
+  pub(crate) fn pairs(
+    &self,
+    pairs: &[(PathBuf, PathBuf)],
+    filesystem: &dyn Filesystem,
+  ) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let sources = pairs
+      .iter()
+      .map(|(source, _)| source)
+      .collect::<HashSet<&PathBuf>>();
+
+    let mut companions = BTreeMap::<PathBuf, BTreeSet<PathBuf>>::new();
+
+    let targets = Plan::new(pairs.to_vec()).targets(filesystem);
+
+    for ((source, _), target) in pairs.iter().zip(targets) {
+      if !filesystem.is_file(source) {
+        continue;
+      }
+
+      let (Some(stem), Some(new_stem)) = (
+        source.file_stem().and_then(OsStr::to_str),
+        target.file_stem().and_then(OsStr::to_str),
+      ) else {
+        continue;
+      };
+
+      let directory = source.parent().unwrap_or(Path::new(""));
+
+      let listing = if directory.as_os_str().is_empty() {
+        Path::new(".")
+      } else {
+        directory
+      };
+
+      for name in filesystem.read_dir(listing)? {
+        let Some(suffix) = name
+          .to_str()
+          .and_then(|name| name.strip_prefix(stem))
+          .and_then(|rest| rest.strip_prefix('.'))
+        else {
+          continue;
+        };
+
+        let companion = directory.join(&name);
+
+        if sources.contains(&companion)
+          || !filesystem.is_file(&companion)
+          || !self.matches(suffix)
+        {
+          continue;
+        }
+
+        let destination = target.with_file_name(format!("{new_stem}.{suffix}"));
+
+        if destination != companion {
+          companions.entry(companion).or_default().insert(destination);
+        }
+      }
+    }
+
+    let ambiguous = companions
+      .iter()
+      .filter(|(_, destinations)| destinations.len() > 1)
+      .map(|(companion, _)| companion.display().to_string())
+      .collect::<Vec<String>>();
+
+    if !ambiguous.is_empty() {
+      return Err(Error::AmbiguousCompanions {
+        companions: ambiguous,
+      });
+    }
+
+    Ok(
+      companions
+        .into_iter()
+        .filter_map(|(companion, destinations)| {
+          destinations
+            .into_iter()
+            .next()
+            .map(|destination| (companion, destination))
+        })
+        .collect(),
+    )
+  }
+}
@@ -0,0 +1,151 @@
+use super::*;
+
+const RESERVED: [&str; 5] = ["config", "help", "preset", "sources", "version"];
+
+/// Defaults for command-line flags, read from a TOML file whose keys are
+/// long flag names, with named presets of further defaults under `presets`.
+#[derive(Debug)]
+pub(crate) struct Config {
+  path: PathBuf,
+  table: Table,
+}
+
+impl Config {
+  fn argument(&self, key: &str, value: &Value) -> Result<OsString> {
+    let value = match value {
+      Value::Float(value) => value.to_string(),
+      Value::Integer(value) => value.to_string(),
+      Value::String(value) => value.clone(),
+      _ => {
+        return Err(self.error(&format!(
+          "`{key}` must be a string, a number, or an array of them"
+        )));
+      }
+    };
+
+    Ok(format!("--{key}={value}").into())
+  }
+
+  /// Turns the defaults, overlaid with those of `preset`, into flags for
+  /// `command`, leaving out any flag that `given` reports as already passed
+  /// on the command line, along with any flag conflicting with one.
+  pub(crate) fn arguments(
+    &self,
+    command: &clap::Command,
+    preset: Option<&str>,
+    given: impl Fn(&Arg) -> bool,
+  ) -> Result<Vec<OsString>> {
+    let mut table = self.table.clone();
+
+    let presets = match table.remove("presets") {
+      Some(Value::Table(presets)) => presets,
+      Some(_) => return Err(self.error("`presets` must be a table")),
+      None => Table::new(),
+    };
+
+    if let Some(name) = preset {
+      match presets.get(name) {
+        Some(Value::Table(preset)) => table.extend(preset.clone()),
+        Some(_) => {
+          return Err(self.error(&format!("preset `{name}` must be a table")));
+        }
+        None => return Err(self.error(&format!("no preset named `{name}`"))),
+      }
+    }
+
+    let mut arguments = Vec::new();
+
+    for (key, value) in &table {
+      let Some(arg) = command.get_arguments().find(|arg| {
+        arg.get_long() == Some(key) && !RESERVED.contains(&key.as_str())
+      }) else {
+        return Err(self.error(&format!("unknown flag `{key}`")));
+      };
+
+      if given(arg)
+        || command.get_arguments().any(|other| {
+          given(other)
+            && (command.get_arg_conflicts_with(arg).contains(&other)
+              || command.get_arg_conflicts_with(other).contains(&arg))
+        })
+      {
+        continue;
+      }
+
+      let count = matches!(arg.get_action(), ArgAction::Count);
+
+      let flag = matches!(arg.get_action(), ArgAction::SetTrue);
+
+      match value {
+        Value::Integer(times) if count => {
+          for _ in 0..*times {
+            arguments.push(format!("--{key}").into());
+          }
+        }
+        _ if count => {
+          return Err(self.error(&format!("`{key}` must be a number")));
+        }
+        Value::Boolean(enabled) if flag => {
+          if *enabled {
+            arguments.push(format!("--{key}").into());
+          }
+        }
+        _ if flag => {
+          return Err(self.error(&format!("`{key}` must be a boolean")));
+        }
+        Value::Array(values) => {
+          for value in values {
+            arguments.push(self.argument(key, value)?);
+          }
+        }
+        value => arguments.push(self.argument(key, value)?),
+      }
+    }
+
+    Ok(arguments)
+  }
+
+  /// The default configuration file, `$XDG_CONFIG_HOME/edmv/config.toml`,
+  /// falling back to `~/.config` if `XDG_CONFIG_HOME` isn't set.
+  pub(crate) fn default_path() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+      .filter(|directory| !directory.is_empty())
+      .map(PathBuf::from)
+      .or_else(|| {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"))
+      })
+      .map(|directory| {
+        directory.join(env!("CARGO_PKG_NAME")).join("config.toml")
+      })
+  }
+
+  fn error(&self, message: &str) -> Error {
+    Error::Config {
+      message: message.to_owned(),
+      path: self.path.clone(),
+    }
+  }
+
+  /// Reads the configuration file at `path`, which is treated as empty if
+  /// it doesn't exist, unless it is `required`.
+  pub(crate) fn load(path: PathBuf, required: bool) -> Result<Self> {
+    if !required && !path.exists() {
+      return Ok(Self {
+        path,
+        table: Table::new(),
+      });
+    }
+
+    let content = fs::read_to_string(&path).map_err(|error| Error::Config {
+      message: error.to_string(),
+      path: path.clone(),
+    })?;
+
+    let table = content.parse::<Table>().map_err(|error| Error::Config {
+      message: error.to_string().trim_end().to_owned(),
+      path: path.clone(),
+    })?;
+
+    Ok(Self { path, table })
+  }
+}
@@ -0,0 +1,231 @@
+use super::*;
+
+/// A text file containing references to renamed paths, which are rewritten
+/// once the renames are made.
+#[derive(Debug)]
+pub(crate) struct Document {
+  content: String,
+  destination: PathBuf,
+  path: PathBuf,
+  references: Vec<Reference>,
+}
+
+impl Document {
+  /// Writes the document, with every reference rewritten, to wherever it
+  /// ends up after renaming.
+  pub(crate) fn apply(&self) -> Result {
+    if fs::read_to_string(&self.destination)? != self.content {
+      return Err(Error::ChangedDocument {
+        path: self.path.clone(),
+      });
+    }
+
+    fs::write(&self.destination, self.rewritten())?;
+
+    Ok(())
+  }
+
+  pub(crate) fn content(&self) -> &str {
+    &self.content
+  }
+
+  pub(crate) fn destination(&self) -> &Path {
+    &self.destination
+  }
+
+  /// Returns where `path` ends up after `moves`, if it is moved at all,
+  /// either directly or along with a directory containing it.
+  pub(crate) fn moved(
+    path: &Path,
+    moves: &[(PathBuf, PathBuf)],
+  ) -> Option<PathBuf> {
+    moves
+      .iter()
+      .filter_map(|(source, destination)| {
+        path.strip_prefix(source).ok().map(|rest| {
+          if rest.as_os_str().is_empty() {
+            (source, destination.clone())
+          } else {
+            (source, destination.join(rest))
+          }
+        })
+      })
+      .max_by_key(|(source, _)| source.components().count())
+      .map(|(_, destination)| destination)
+  }
+
+  pub(crate) fn path(&self) -> &Path {
+    &self.path
+  }
+
+  pub(crate) fn references(&self) -> &[Reference] {
+    &self.references
+  }
+
+  pub(crate) fn relative(path: &Path, base: &Path) -> String {
+    let path = path.components().collect::<Vec<Component>>();
+    let base = base.components().collect::<Vec<Component>>();
+
+    let common = path
+      .iter()
+      .zip(&base)
+      .take_while(|(path, base)| path == base)
+      .count();
+
+    let relative = iter::repeat_n("..".to_owned(), base.len() - common)
+      .chain(
+        path[common..]
+          .iter()
+          .map(|component| component.as_os_str().to_string_lossy().into()),
+      )
+      .collect::<Vec<String>>();
+
+    if relative.is_empty() {
+      ".".to_owned()
+    } else {
+      relative.join("/")
+    }
+  }
+
+  fn rewrite(
+    token: &str,
+    directory: &Path,
+    base: &Path,
+    moves: &[(PathBuf, PathBuf)],
+    filesystem: &dyn Filesystem,
+  ) -> Result<Option<String>> {
+    let absolute = Path::new(token).is_absolute();
+
+    let target = Path::new(token).absolutize_from(directory)?.into_owned();
+
+    let target = match Self::moved(&target, moves) {
+      Some(moved) => moved,
+      None if !absolute && base != directory && filesystem.exists(&target) => {
+        target
+      }
+      None => return Ok(None),
+    };
+
+    let mut replacement = if absolute {
+      target.display().to_string()
+    } else {
+      Self::relative(&target, base)
+    };
+
+    if token.starts_with("./") && !replacement.starts_with('.') {
+      replacement.insert_str(0, "./");
+    }
+
+    if token.ends_with('/') && !replacement.ends_with('/') {
+      replacement.push('/');
+    }
+
+    Ok((replacement != token).then_some(replacement))
+  }
+
+  /// Returns the content with every reference rewritten.
+  pub(crate) fn rewritten(&self) -> String {
+    let mut content = String::new();
+
+    let mut last = 0;
+
+    for reference in &self.references {
+      content.push_str(&self.content[last..reference.range.start]);
+      content.push_str(&reference.replacement);
+      last = reference.range.end;
+    }
+
+    content.push_str(&self.content[last..]);
+
+    content
+  }
+
+  /// Finds the references in the file at `path` to any source in `moves`,
+  /// which map absolute sources to where they end up. If the file itself is
+  /// moved, its relative references to other existing paths are rewritten
+  /// too. Files which aren't valid UTF-8 are skipped.
+  pub(crate) fn scan(
+    path: PathBuf,
+    moves: &[(PathBuf, PathBuf)],
+    filesystem: &dyn Filesystem,
+  ) -> Result<Option<Self>> {
+    let content = match fs::read_to_string(&path) {
+      Ok(content) => content,
+      Err(error) if error.kind() == io::ErrorKind::InvalidData => {
+        return Ok(None);
+      }
+      Err(error) => return Err(error.into()),
+    };
+
+    let absolute = path.absolutize()?.into_owned();
+
+    let destination =
+      Self::moved(&absolute, moves).unwrap_or_else(|| absolute.clone());
+
+    let (Some(directory), Some(base)) =
+      (absolute.parent(), destination.parent())
+    else {
+      return Ok(None);
+    };
+
+    let mut references = Vec::new();
+
+    let mut offset = 0;
+
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+      for (start, token) in Self::tokens(line) {
+        if let Some(replacement) =
+          Self::rewrite(token, directory, base, moves, filesystem)?
+        {
+          references.push(Reference {
+            line: index + 1,
+            original: token.to_owned(),
+            range: offset + start..offset + start + token.len(),
+            replacement,
+          });
+        }
+      }
+
+      offset += line.len();
+    }
+
+    Ok((!references.is_empty()).then_some(Self {
+      content,
+      destination,
+      path,
+      references,
+    }))
+  }
+
+  /// Splits `line` into runs of characters that may form a path, such as
+  /// the targets of Markdown links, HTML attributes and quoted strings,
+  /// keeping only those that contain a `/` or a `.`, so that plain words
+  /// are left alone.
+  fn tokens(line: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+
+    let mut start = None;
+
+    for (index, character) in
+      line.char_indices().chain(iter::once((line.len(), ' ')))
+    {
+      let path = character.is_alphanumeric() || "%+-./@_".contains(character);
+
+      match (start, path) {
+        (None, true) => start = Some(index),
+        (Some(begin), false) => {
+          let token = line[begin..index].trim_end_matches('.');
+
+          if token.contains(['.', '/']) {
+            tokens.push((begin, token));
+          }
+
+          start = None;
+        }
+        _ => {}
+      }
+    }
+
+    tokens
+  }
+}
@@ -0,0 +1,17 @@
+use super::*;
+
+/// The kind of path kept by `--type`.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub(crate) enum EntryType {
+  Dir,
+  File,
+}
+
+impl EntryType {
+  pub(crate) fn matches(self, kind: Kind) -> bool {
+    match self {
+      Self::Dir => kind == Kind::Directory,
+      Self::File => kind == Kind::File,
+    }
+  }
+}
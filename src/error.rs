@@ -0,0 +1,358 @@
+use super::*;
+
+/// An error, each kind of which exits with its own status.
+#[derive(Debug)]
+pub enum Error {
+  Aborted,
+  AmbiguousCompanions {
+    companions: Vec<String>,
+  },
+  ChangedDocument {
+    path: PathBuf,
+  },
+  ChangedLink {
+    path: PathBuf,
+  },
+  ChangedSources {
+    sources: Vec<String>,
+  },
+  CircularRenames {
+    sources: Vec<String>,
+  },
+  Config {
+    message: String,
+    path: PathBuf,
+  },
+  Conflicting {
+    operations: Vec<String>,
+  },
+  CountMismatch {
+    expected: usize,
+    received: usize,
+  },
+  Create {
+    path: PathBuf,
+    source: io::Error,
+  },
+  DirectoryToFile {
+    operations: Vec<String>,
+  },
+  Duplicate {
+    destinations: Vec<String>,
+  },
+  EditorLaunch {
+    editor: String,
+    source: io::Error,
+  },
+  EditorStatus,
+  EmptyDestinations {
+    sources: Vec<String>,
+  },
+  Existing {
+    destinations: Vec<String>,
+  },
+  FileParents {
+    names: Vec<String>,
+  },
+  HookLaunch {
+    command: String,
+    source: io::Error,
+  },
+  HookStatus {
+    command: String,
+    status: process::ExitStatus,
+  },
+  Io(io::Error),
+  Journal {
+    message: String,
+    path: PathBuf,
+  },
+  Journaled {
+    path: PathBuf,
+    source: Box<Error>,
+  },
+  Locked {
+    directory: PathBuf,
+    holder: String,
+  },
+  MissingParent {
+    destinations: Vec<String>,
+  },
+  MissingSources {
+    sources: Vec<String>,
+  },
+  NestedRenames {
+    sources: Vec<String>,
+  },
+  NonExistent {
+    paths: Vec<String>,
+  },
+  NotTerminal,
+  Pattern {
+    pattern: String,
+    source: PatternError,
+  },
+  PostHooks {
+    failed: usize,
+  },
+  Rename {
+    from: PathBuf,
+    source: io::Error,
+    to: PathBuf,
+  },
+  RepeatedSources {
+    sources: Vec<String>,
+  },
+  SeparatedNames {
+    names: Vec<String>,
+  },
+  SkippedDestinations {
+    destinations: Vec<String>,
+  },
+  TabbedPaths {
+    paths: Vec<String>,
+  },
+  UnknownIds {
+    ids: Vec<String>,
+  },
+  UnnamedLines {
+    lines: Vec<String>,
+  },
+  UnseparatedLines {
+    lines: Vec<String>,
+  },
+  Validation {
+    problems: Vec<Error>,
+  },
+}
+
+impl Display for Error {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      Self::Aborted => write!(f, "Buffer is empty, aborting"),
+      Self::AmbiguousCompanions { companions } => write!(
+        f,
+        "Found companion(s) shared by files renamed to different stems: {}",
+        companions.join(", ")
+      ),
+      Self::ChangedDocument { path } => write!(
+        f,
+        "Document `{}` changed while renaming, its references were not updated",
+        path.display()
+      ),
+      Self::ChangedLink { path } => write!(
+        f,
+        "Link `{}` changed while renaming, its target was not updated",
+        path.display()
+      ),
+      Self::ChangedSources { sources } => write!(
+        f,
+        "Found source(s) that were changed or added: {}, only the destination column may be edited",
+        sources.join(", ")
+      ),
+      Self::CircularRenames { sources } => write!(
+        f,
+        "Found directory(s) moved inside themselves: {}",
+        sources.join(", ")
+      ),
+      Self::Config { message, path } => write!(
+        f,
+        "Invalid configuration file `{}`: {message}",
+        path.display()
+      ),
+      Self::Conflicting { operations } => write!(
+        f,
+        "Found conflicting operation(s): {}, use --resolve to properly handle the conflicts",
+        operations.join(", ")
+      ),
+      Self::CountMismatch { expected, received } => write!(
+        f,
+        "Destination count mismatch, should be {expected} but received {received}"
+      ),
+      Self::Create { path, source } => {
+        write!(f, "Failed to create `{}`: {source}", path.display())
+      }
+      Self::DirectoryToFile { operations } => write!(
+        f,
+        "Found directory to file operation(s): {}",
+        operations.join(", ")
+      ),
+      Self::Duplicate { destinations } => write!(
+        f,
+        "Found duplicate destination(s): {}",
+        destinations.join(", ")
+      ),
+      Self::EditorLaunch { editor, source } => write!(
+        f,
+        "Failed to launch editor `{editor}`: {source}, pass --editor or use --tui to edit without one"
+      ),
+      Self::EditorStatus => {
+        write!(f, "Failed to open temporary file in editor")
+      }
+      Self::EmptyDestinations { sources } => write!(
+        f,
+        "Found empty destination(s) for: {}",
+        sources.join(", ")
+      ),
+      Self::Existing { destinations } => write!(
+        f,
+        "Found destination(s) that already exist: {}, use --force to overwrite",
+        destinations.join(", ")
+      ),
+      Self::FileParents { names } => write!(
+        f,
+        "Found path(s) indented beneath a file: {}",
+        names.join(", ")
+      ),
+      Self::HookLaunch { command, source } => {
+        write!(f, "Failed to launch hook `{command}`: {source}")
+      }
+      Self::HookStatus { command, status } => {
+        write!(f, "Hook `{command}` failed with {status}")
+      }
+      Self::Io(source) => write!(f, "{source}"),
+      Self::Journal { message, path } => {
+        write!(f, "Journal `{}`: {message}", path.display())
+      }
+      Self::Journaled { path, source } => write!(
+        f,
+        "{source}\nnote: The rest of the run is recorded in journal `{}`, finish it with `edmv resume` once the problem is fixed, or remove the journal",
+        path.display()
+      ),
+      Self::Locked { directory, holder } => write!(
+        f,
+        "Directory `{}` is locked by another edmv run (PID {}), pass --wait to wait for it",
+        directory.display(),
+        if holder.is_empty() { "unknown" } else { holder },
+      ),
+      Self::MissingParent { destinations } => write!(
+        f,
+        "Found destination(s) placed within a non-existent directory: {}",
+        destinations.join(", ")
+      ),
+      Self::MissingSources { sources } => write!(
+        f,
+        "Found source(s) missing from the buffer: {}",
+        sources.join(", ")
+      ),
+      Self::NestedRenames { sources } => write!(
+        f,
+        "Found rename(s) within renamed directories: {}, which --resolve cannot perform",
+        sources.join(", ")
+      ),
+      Self::NonExistent { paths } => {
+        write!(f, "Found non-existent path(s): {}", paths.join(", "))
+      }
+      Self::NotTerminal => {
+        write!(f, "--tui needs stdin and stdout to be a terminal")
+      }
+      Self::Pattern { pattern, source } => {
+        write!(f, "Invalid reference pattern `{pattern}`: {source}")
+      }
+      Self::PostHooks { failed } => {
+        write!(f, "{failed} post-hook(s) failed after renaming")
+      }
+      Self::Rename { from, source, to } => write!(
+        f,
+        "Failed to rename `{}` to `{}`: {source}",
+        from.display(),
+        to.display()
+      ),
+      Self::RepeatedSources { sources } => write!(
+        f,
+        "Found source(s) listed more than once: {}",
+        sources.join(", ")
+      ),
+      Self::SeparatedNames { names } => write!(
+        f,
+        "Found name(s) containing a path separator where only a single component is allowed: {}",
+        names.join(", ")
+      ),
+      Self::SkippedDestinations { destinations } => write!(
+        f,
+        "Found destination(s) that still exist because their own rename was skipped: {}",
+        destinations.join(", ")
+      ),
+      Self::TabbedPaths { paths } => write!(
+        f,
+        "Found path(s) containing a tab, which the dual format cannot represent: {}",
+        paths.join(", ")
+      ),
+      Self::UnknownIds { ids } => {
+        write!(f, "Found unknown ID(s): {}", ids.join(", "))
+      }
+      Self::UnnamedLines { lines } => write!(
+        f,
+        "Found new line(s) without a name: {}",
+        lines.join(", ")
+      ),
+      Self::UnseparatedLines { lines } => write!(
+        f,
+        "Found line(s) without a tab separator: {}",
+        lines.join(", ")
+      ),
+      Self::Validation { problems } => {
+        write!(f, "Found {} kinds of problem:", problems.len())?;
+
+        problems
+          .iter()
+          .try_for_each(|problem| write!(f, "\n  {problem}"))
+      }
+    }
+  }
+}
+
+impl From<io::Error> for Error {
+  fn from(error: io::Error) -> Self {
+    Self::Io(error)
+  }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+  /// The process exit status for this error.
+  ///
+  /// These are stable, and documented in the readme.
+  #[must_use]
+  pub fn code(&self) -> i32 {
+    match self {
+      Self::Create { .. } | Self::Io(_) | Self::Rename { .. } => 1,
+      Self::EditorLaunch { .. } | Self::EditorStatus => 3,
+      Self::Aborted => 4,
+      Self::NonExistent { .. } => 5,
+      Self::CountMismatch { .. } => 6,
+      Self::ChangedSources { .. }
+      | Self::EmptyDestinations { .. }
+      | Self::FileParents { .. }
+      | Self::MissingSources { .. }
+      | Self::RepeatedSources { .. }
+      | Self::SeparatedNames { .. }
+      | Self::TabbedPaths { .. }
+      | Self::UnknownIds { .. }
+      | Self::UnnamedLines { .. }
+      | Self::UnseparatedLines { .. } => 7,
+      Self::Duplicate { .. } => 8,
+      Self::Existing { .. } => 9,
+      Self::CircularRenames { .. }
+      | Self::Conflicting { .. }
+      | Self::NestedRenames { .. } => 10,
+      Self::DirectoryToFile { .. } => 11,
+      Self::MissingParent { .. } => 12,
+      Self::Validation { .. } => 13,
+      Self::HookLaunch { .. }
+      | Self::HookStatus { .. }
+      | Self::PostHooks { .. }
+      | Self::SkippedDestinations { .. } => 14,
+      Self::ChangedDocument { .. }
+      | Self::ChangedLink { .. }
+      | Self::Pattern { .. } => 15,
+      Self::AmbiguousCompanions { .. } => 16,
+      Self::Config { .. } => 17,
+      Self::Journaled { source, .. } => source.code(),
+      Self::Journal { .. } => 18,
+      Self::Locked { .. } => 19,
+      Self::NotTerminal => 20,
+    }
+  }
+}
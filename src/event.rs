@@ -0,0 +1,20 @@
+use super::*;
+
+/// Progress reported while a [`Plan`] is executed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event<'a> {
+  /// A new, empty path is about to be created.
+  Create { kind: Kind, path: &'a Path },
+  /// A new, empty path has been created.
+  Created { kind: Kind, path: &'a Path },
+  /// A single rename call is about to be made.
+  Rename { from: &'a Path, to: &'a Path },
+  /// A source has reached its final destination.
+  Renamed {
+    destination: &'a Path,
+    source: &'a Path,
+  },
+  /// The stage numbered `index`, counting from zero, of `stages` is about to
+  /// start.
+  Stage { index: usize, stages: usize },
+}
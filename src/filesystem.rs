@@ -0,0 +1,56 @@
+use super::*;
+
+/// The operations edmv needs from a filesystem, so that rename plans can be
+/// validated and executed against something other than the disk.
+pub trait Filesystem {
+  /// Creates an empty file or directory at `path`.
+  ///
+  /// # Errors
+  ///
+  /// Returns an error if `path` already exists or its parent does not.
+  fn create(&mut self, path: &Path, kind: Kind) -> io::Result<()>;
+
+  /// Returns whether `path` exists.
+  fn exists(&self, path: &Path) -> bool {
+    self.metadata(path).is_some()
+  }
+
+  /// Returns whether `path` exists and is a directory.
+  fn is_dir(&self, path: &Path) -> bool {
+    self
+      .metadata(path)
+      .is_some_and(|metadata| metadata.kind == Kind::Directory)
+  }
+
+  /// Returns whether `path` exists and is a file.
+  fn is_file(&self, path: &Path) -> bool {
+    self
+      .metadata(path)
+      .is_some_and(|metadata| metadata.kind == Kind::File)
+  }
+
+  /// Returns the metadata of `path`, following symbolic links, or `None` if
+  /// it does not exist.
+  fn metadata(&self, path: &Path) -> Option<Metadata>;
+
+  /// Returns the names of the entries within the directory `path`.
+  ///
+  /// # Errors
+  ///
+  /// Returns an error if `path` cannot be read as a directory.
+  fn read_dir(&self, path: &Path) -> io::Result<Vec<OsString>>;
+
+  /// Renames `from` to `to`, replacing `to` if it exists.
+  ///
+  /// # Errors
+  ///
+  /// Returns an error if the rename fails.
+  fn rename(&mut self, from: &Path, to: &Path) -> io::Result<()>;
+
+  /// Creates an empty temporary file or directory and returns its path.
+  ///
+  /// # Errors
+  ///
+  /// Returns an error if the temporary path cannot be created.
+  fn temporary(&mut self, kind: Kind) -> io::Result<PathBuf>;
+}
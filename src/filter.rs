@@ -0,0 +1,45 @@
+use super::*;
+
+/// Decides which paths may be listed in the buffer, from `--type`, `--ext`
+/// and `--exclude`.
+#[derive(Debug)]
+pub(crate) struct Filter<'a> {
+  pub(crate) entry_type: Option<EntryType>,
+  pub(crate) excludes: &'a [Pattern],
+  pub(crate) extensions: &'a [String],
+}
+
+impl Filter<'_> {
+  /// Whether `path`, or any of its components, matches an `--exclude`
+  /// pattern.
+  pub(crate) fn excludes(&self, path: &Path) -> bool {
+    self.excludes.iter().any(|pattern| {
+      pattern.matches_path(path)
+        || path.components().any(|component| match component {
+          Component::Normal(name) => pattern.matches(&name.to_string_lossy()),
+          _ => false,
+        })
+    })
+  }
+
+  /// Whether a path of `kind` is kept by `--type` and `--ext`. Extensions
+  /// only apply to files.
+  pub(crate) fn includes(&self, path: &Path, kind: Kind) -> bool {
+    if self
+      .entry_type
+      .is_some_and(|entry_type| !entry_type.matches(kind))
+    {
+      return false;
+    }
+
+    kind == Kind::Directory
+      || self.extensions.is_empty()
+      || path.extension().is_some_and(|extension| {
+        self.extensions.iter().any(|wanted| {
+          wanted
+            .trim_start_matches('.')
+            .eq_ignore_ascii_case(&extension.to_string_lossy())
+        })
+      })
+  }
+}
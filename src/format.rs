@@ -0,0 +1,9 @@
+use super::*;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub(crate) enum Format {
+  Dual,
+  #[default]
+  Single,
+  Tree,
+}
@@ -0,0 +1,258 @@
+//! Randomized checks of the rename planner, shared by the property tests and
+//! the fuzz target.
+
+use super::*;
+
+const NAMES: [&str; 8] = ["a", "b", "c", "d", "e", "f", "g", "h"];
+
+/// A path in the initial tree of a [`Scenario`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Entry {
+  Directory { child: bool },
+  File,
+}
+
+/// A tree of paths within a single directory, along with renames between
+/// them.
+#[derive(Clone, Debug)]
+pub struct Scenario {
+  pub entries: [Option<Entry>; NAMES.len()],
+  pub pairs: Vec<(usize, usize)>,
+  pub resolve: bool,
+}
+
+impl Scenario {
+  /// Checks that validating and executing the scenario's renames against an
+  /// in-memory filesystem either fails validation, or moves every source to
+  /// its intended destination without losing or merging any other path.
+  ///
+  /// # Panics
+  ///
+  /// Panics if a validated plan fails to execute, or if the resulting tree
+  /// differs from the intended one.
+  pub fn check(&self) {
+    let root = PathBuf::from("/edmv");
+
+    let path = |index: usize| root.join(NAMES[index]);
+
+    let mut memory = Memory::new();
+
+    memory.create_dir_all(&root).unwrap();
+
+    for (index, entry) in self.entries.iter().enumerate() {
+      match entry {
+        Some(Entry::Directory { child }) => {
+          memory.create_dir_all(&path(index)).unwrap();
+
+          if *child {
+            memory.create_file(&path(index).join("child")).unwrap();
+          }
+        }
+        Some(Entry::File) => memory.create_file(&path(index)).unwrap(),
+        None => {}
+      }
+    }
+
+    let pairs = self
+      .pairs
+      .iter()
+      .map(|&(source, destination)| {
+        (source % NAMES.len(), destination % NAMES.len())
+      })
+      .filter(|(source, destination)| {
+        source != destination && self.entries[*source].is_some()
+      })
+      .fold(Vec::new(), |mut pairs, (source, destination)| {
+        if pairs.iter().all(|(existing, _)| *existing != source) {
+          pairs.push((source, destination));
+        }
+        pairs
+      });
+
+    let plan = Plan::new(
+      pairs
+        .iter()
+        .map(|&(source, destination)| (path(source), path(destination)))
+        .collect(),
+    );
+
+    if plan.validate(&memory, true, self.resolve).is_err() {
+      return;
+    }
+
+    let inode = |memory: &Memory, path: &Path| {
+      memory.metadata(path).and_then(|metadata| metadata.inode)
+    };
+
+    let mut expected = BTreeMap::new();
+
+    for (index, entry) in self.entries.iter().enumerate() {
+      if entry.is_some() && pairs.iter().all(|(source, _)| *source != index) {
+        expected.insert(path(index), inode(&memory, &path(index)));
+      }
+    }
+
+    let sources = pairs.iter().map(|(source, _)| *source).collect::<Vec<_>>();
+
+    for &(source, destination) in &pairs {
+      let target = if memory.is_dir(&path(destination))
+        && !sources.contains(&destination)
+      {
+        path(destination).join(NAMES[source])
+      } else {
+        path(destination)
+      };
+
+      if matches!(self.entries[source], Some(Entry::Directory { child: true }))
+      {
+        expected.insert(
+          target.join("child"),
+          inode(&memory, &path(source).join("child")),
+        );
+      }
+
+      expected.insert(target, inode(&memory, &path(source)));
+    }
+
+    for (index, entry) in self.entries.iter().enumerate() {
+      if let Some(Entry::Directory { child: true }) = entry {
+        let child = path(index).join("child");
+
+        if expected.contains_key(&path(index)) && !sources.contains(&index) {
+          expected.insert(child.clone(), inode(&memory, &child));
+        }
+      }
+    }
+
+    if let Err(error) = plan.execute(&mut memory, self.resolve, |_| {}) {
+      panic!("failed to execute validated plan {self:?}: {error}");
+    }
+
+    let mut actual = BTreeMap::new();
+
+    let mut directories = vec![root.clone()];
+
+    while let Some(directory) = directories.pop() {
+      for name in memory.read_dir(&directory).unwrap() {
+        let path = directory.join(name);
+
+        if memory.is_dir(&path) {
+          directories.push(path.clone());
+        }
+
+        actual.insert(path.clone(), inode(&memory, &path));
+      }
+    }
+
+    assert_eq!(actual, expected, "unexpected tree for {self:?}");
+
+    let temporary = env::temp_dir();
+
+    if memory.exists(&temporary) {
+      assert_eq!(
+        memory.read_dir(&temporary).unwrap(),
+        Vec::<OsString>::new(),
+        "temporary paths left behind for {self:?}"
+      );
+    }
+  }
+
+  /// Decodes a scenario from arbitrary bytes.
+  #[must_use]
+  pub fn from_bytes(bytes: &[u8]) -> Self {
+    let mut entries = [None; NAMES.len()];
+
+    for (entry, byte) in entries.iter_mut().zip(bytes.iter().skip(1)) {
+      *entry = match byte % 4 {
+        0 => None,
+        1 => Some(Entry::File),
+        kind => Some(Entry::Directory { child: kind == 3 }),
+      };
+    }
+
+    Self {
+      entries,
+      pairs: bytes
+        .iter()
+        .skip(NAMES.len() + 1)
+        .collect::<Vec<_>>()
+        .chunks_exact(2)
+        .map(|chunk| (usize::from(*chunk[0]), usize::from(*chunk[1])))
+        .collect(),
+      resolve: bytes.first().is_some_and(|byte| byte % 2 == 1),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {super::*, proptest::prelude::*};
+
+  proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2048))]
+
+    #[test]
+    fn renames_preserve_every_path(
+      bytes in proptest::collection::vec(any::<u8>(), 9..48)
+    ) {
+      Scenario::from_bytes(&bytes).check();
+    }
+  }
+
+  #[test]
+  fn swaps_directories_with_contents() {
+    Scenario {
+      entries: [
+        Some(Entry::Directory { child: true }),
+        Some(Entry::Directory { child: true }),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+      ],
+      pairs: vec![(0, 1), (1, 0)],
+      resolve: true,
+    }
+    .check();
+  }
+
+  #[test]
+  fn rotates_files() {
+    Scenario {
+      entries: [
+        Some(Entry::File),
+        Some(Entry::File),
+        Some(Entry::File),
+        None,
+        None,
+        None,
+        None,
+        None,
+      ],
+      pairs: vec![(0, 1), (1, 2), (2, 0)],
+      resolve: true,
+    }
+    .check();
+  }
+
+  #[test]
+  fn moves_into_existing_directory() {
+    Scenario {
+      entries: [
+        Some(Entry::File),
+        Some(Entry::Directory { child: true }),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+      ],
+      pairs: vec![(0, 1)],
+      resolve: false,
+    }
+    .check();
+  }
+}
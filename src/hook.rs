@@ -0,0 +1,57 @@
+use super::*;
+
+/// A shell command run around renames, passed paths both as positional
+/// arguments and through the environment.
+pub(crate) struct Hook<'a>(pub(crate) &'a str);
+
+impl Hook<'_> {
+  /// Runs the hook once for a whole batch of `count` renames.
+  pub(crate) fn batch(&self, count: usize) -> Result {
+    self.run(&[], &[("EDMV_COUNT", OsString::from(count.to_string()))])
+  }
+
+  /// Runs the hook for a single rename of `source` to `destination`.
+  pub(crate) fn pair(&self, source: &Path, destination: &Path) -> Result {
+    self.run(
+      &[source.as_os_str(), destination.as_os_str()],
+      &[
+        ("EDMV_DESTINATION", destination.as_os_str().to_owned()),
+        ("EDMV_SOURCE", source.as_os_str().to_owned()),
+      ],
+    )
+  }
+
+  fn run(
+    &self,
+    arguments: &[&OsStr],
+    environment: &[(&str, OsString)],
+  ) -> Result {
+    let mut command = if cfg!(windows) {
+      let mut command = Command::new("cmd");
+      command.arg("/C").arg(self.0);
+      command
+    } else {
+      let mut command = Command::new("sh");
+      command.arg("-c").arg(self.0).arg(env!("CARGO_PKG_NAME"));
+      command
+    };
+
+    let status = command
+      .args(arguments)
+      .envs(environment.iter().map(|(key, value)| (key, value)))
+      .status()
+      .map_err(|source| Error::HookLaunch {
+        command: self.0.to_owned(),
+        source,
+      })?;
+
+    if !status.success() {
+      return Err(Error::HookStatus {
+        command: self.0.to_owned(),
+        status,
+      });
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,103 @@
+use super::*;
+
+const FILES: [&str; 2] = [".gitignore", ".edmvignore"];
+
+#[derive(Debug)]
+struct Rule {
+  anchored: bool,
+  directory: bool,
+  negated: bool,
+  pattern: Pattern,
+}
+
+/// The rules of the `.gitignore` and `.edmvignore` files within a directory.
+///
+/// This supports the common subset of the gitignore syntax: comments,
+/// negation with `!`, directory-only patterns ending in `/`, and patterns
+/// containing a `/`, which are matched relative to the directory.
+#[derive(Debug)]
+pub(crate) struct Ignore {
+  directory: PathBuf,
+  rules: Vec<Rule>,
+}
+
+impl Ignore {
+  /// Whether `path`, a `kind` within this directory, is ignored, or `None`
+  /// if no rule applies to it.
+  pub(crate) fn ignores(&self, path: &Path, kind: Kind) -> Option<bool> {
+    let relative = path.strip_prefix(&self.directory).ok()?;
+
+    let name = relative.file_name()?.to_string_lossy();
+
+    let relative = relative
+      .components()
+      .map(|component| component.as_os_str().to_string_lossy())
+      .collect::<Vec<_>>()
+      .join("/");
+
+    let options = MatchOptions {
+      require_literal_separator: true,
+      ..MatchOptions::new()
+    };
+
+    self
+      .rules
+      .iter()
+      .rev()
+      .find(|rule| {
+        (!rule.directory || kind == Kind::Directory)
+          && if rule.anchored {
+            rule.pattern.matches_with(&relative, options)
+          } else {
+            rule.pattern.matches_with(&name, options)
+          }
+      })
+      .map(|rule| !rule.negated)
+  }
+
+  /// Reads the ignore files in `directory`, skipping those that are missing
+  /// or unreadable, and lines that aren't valid patterns.
+  pub(crate) fn load(directory: &Path) -> Self {
+    let mut rules = Vec::new();
+
+    for file in FILES {
+      let Ok(content) = fs::read_to_string(directory.join(file)) else {
+        continue;
+      };
+
+      for line in content.lines() {
+        let line = line.trim_end();
+
+        if line.is_empty() || line.starts_with('#') {
+          continue;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+          Some(line) => (true, line),
+          None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+
+        let (directory, line) = match line.strip_suffix('/') {
+          Some(line) => (true, line),
+          None => (false, line),
+        };
+
+        let anchored = line.contains('/');
+
+        if let Ok(pattern) = Pattern::new(line.trim_start_matches('/')) {
+          rules.push(Rule {
+            anchored,
+            directory,
+            negated,
+            pattern,
+          });
+        }
+      }
+    }
+
+    Self {
+      directory: directory.to_owned(),
+      rules,
+    }
+  }
+}
@@ -0,0 +1,70 @@
+use super::*;
+
+/// A single line of editable text in the terminal UI.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Input {
+  cursor: usize,
+  text: String,
+}
+
+impl Input {
+  fn byte(&self, index: usize) -> usize {
+    self
+      .text
+      .char_indices()
+      .nth(index)
+      .map_or(self.text.len(), |(byte, _)| byte)
+  }
+
+  pub(crate) fn cursor(&self) -> usize {
+    self.cursor
+  }
+
+  pub(crate) fn handle(&mut self, key: KeyEvent) {
+    let control = key.modifiers.contains(KeyModifiers::CONTROL);
+
+    match key.code {
+      KeyCode::Backspace if self.cursor > 0 => {
+        self.cursor -= 1;
+        self.text.remove(self.byte(self.cursor));
+      }
+      KeyCode::Char('a') if control => self.cursor = 0,
+      KeyCode::Char('e') if control => self.cursor = self.len(),
+      KeyCode::Char('u') if control => {
+        self.text.replace_range(..self.byte(self.cursor), "");
+        self.cursor = 0;
+      }
+      KeyCode::Char(character) if !control => {
+        self.text.insert(self.byte(self.cursor), character);
+        self.cursor += 1;
+      }
+      KeyCode::Delete if self.cursor < self.len() => {
+        self.text.remove(self.byte(self.cursor));
+      }
+      KeyCode::End => self.cursor = self.len(),
+      KeyCode::Home => self.cursor = 0,
+      KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+      KeyCode::Right => self.cursor = (self.cursor + 1).min(self.len()),
+      _ => {}
+    }
+  }
+
+  pub(crate) fn into_text(self) -> String {
+    self.text
+  }
+
+  fn len(&self) -> usize {
+    self.text.chars().count()
+  }
+
+  pub(crate) fn new(text: String) -> Self {
+    Self {
+      cursor: text.chars().count(),
+      text,
+    }
+  }
+
+  pub(crate) fn text(&self) -> &str {
+    &self.text
+  }
+}
@@ -0,0 +1,34 @@
+use super::*;
+
+#[derive(Debug)]
+pub(crate) enum Intermediate {
+  Directory(TempDir),
+  File(NamedTempFile),
+}
+
+impl Intermediate {
+  pub(crate) fn keep(self) -> io::Result<()> {
+    match self {
+      Intermediate::Directory(dir) => drop(dir.keep()),
+      Intermediate::File(file) => {
+        drop(file.keep().map_err(|error| error.error)?);
+      }
+    }
+
+    Ok(())
+  }
+
+  pub(crate) fn new(kind: Kind) -> io::Result<Self> {
+    Ok(match kind {
+      Kind::Directory => Intermediate::Directory(TempDir::new()?),
+      Kind::File => Intermediate::File(NamedTempFile::new()?),
+    })
+  }
+
+  pub(crate) fn path(&self) -> &Path {
+    match self {
+      Intermediate::File(file) => file.path(),
+      Intermediate::Directory(dir) => dir.path(),
+    }
+  }
+}
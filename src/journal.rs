@@ -0,0 +1,310 @@
+use super::*;
+
+/// A write-ahead record of the steps of a run, kept until the run finishes so
+/// that `edmv resume` can complete it after an interruption.
+///
+/// Each run writes its own `journal-<PID>.toml` and holds an exclusive lock on
+/// it until it finishes. The lock is released when the process exits however
+/// it does, so a journal that can be locked belongs to a run that was
+/// interrupted, while the journals of live runs are left alone.
+///
+/// Every step is written up front as a `[[step]]` table, and an empty
+/// `[[started]]` table is appended just before each one is made. Every step
+/// before the last one started is therefore complete, while whether the last
+/// one is has to be read from the filesystem.
+///
+/// Like the renames themselves, writes aren't synced to disk, so the journal
+/// survives the process being killed but not a power loss.
+pub(crate) struct Journal {
+  error: Option<io::Error>,
+  file: File,
+  path: PathBuf,
+}
+
+impl Journal {
+  /// Records that the next step is about to be made. A failure to write is
+  /// kept and returned by [`Journal::finish`], rather than interrupting the
+  /// run halfway.
+  pub(crate) fn begin(&mut self) {
+    if self.error.is_none() {
+      if let Err(error) = self.file.write_all(b"[[started]]\n") {
+        self.error = Some(error);
+      }
+    }
+  }
+
+  /// Refuses to start a run touching the absolute `paths` while the journal
+  /// of an earlier one that was interrupted, and touched one of them, or a
+  /// path above or beneath one, is still around.
+  pub(crate) fn check(paths: &BTreeSet<PathBuf>) -> Result {
+    let Some(directory) = Self::directory() else {
+      return Ok(());
+    };
+
+    for mut journal in Self::interrupted(&directory)? {
+      let (steps, _) = journal.read()?;
+
+      let overlaps = steps.iter().flat_map(Step::paths).any(|step| {
+        step.ancestors().any(|ancestor| paths.contains(ancestor))
+          || paths
+            .range(step.to_path_buf()..)
+            .next()
+            .is_some_and(|path| path.starts_with(step))
+      });
+
+      if overlaps {
+        return Err(Self::error(
+          &journal.path,
+          "an earlier run touching the same paths was interrupted, finish it with `edmv resume` or remove the journal",
+        ));
+      }
+    }
+
+    Ok(())
+  }
+
+  /// The directory holding journals, `$XDG_STATE_HOME/edmv`, falling back to
+  /// `~/.local/state` if `XDG_STATE_HOME` isn't set.
+  fn directory() -> Option<PathBuf> {
+    env::var_os("XDG_STATE_HOME")
+      .filter(|directory| !directory.is_empty())
+      .map(PathBuf::from)
+      .or_else(|| {
+        env::var_os("HOME")
+          .map(|home| PathBuf::from(home).join(".local").join("state"))
+      })
+      .map(|directory| directory.join(env!("CARGO_PKG_NAME")))
+  }
+
+  fn error(path: &Path, message: &str) -> Error {
+    Error::Journal {
+      message: message.to_owned(),
+      path: path.to_owned(),
+    }
+  }
+
+  /// Removes the journal once every step has been made.
+  pub(crate) fn finish(self) -> Result {
+    if let Some(error) = self.error {
+      return Err(Self::error(
+        &self.path,
+        &format!("failed to record progress: {error}"),
+      ));
+    }
+
+    fs::remove_file(&self.path)?;
+
+    Ok(())
+  }
+
+  /// Notes on `error`, which stopped a run partway, that the rest of it is
+  /// recorded in `journal` for `edmv resume`, if it has one.
+  pub(crate) fn interrupt(journal: Option<&Self>, error: Error) -> Error {
+    match journal {
+      Some(journal) => Error::Journaled {
+        path: journal.path.clone(),
+        source: Box::new(error),
+      },
+      None => error,
+    }
+  }
+
+  /// Locks and returns the journals in `directory` of runs that were
+  /// interrupted, skipping those still locked by a live run.
+  fn interrupted(directory: &Path) -> Result<Vec<Self>> {
+    let entries = match fs::read_dir(directory) {
+      Ok(entries) => entries,
+      Err(error) if error.kind() == io::ErrorKind::NotFound => {
+        return Ok(Vec::new());
+      }
+      Err(error) => return Err(error.into()),
+    };
+
+    let mut journals = Vec::new();
+
+    for entry in entries {
+      let path = entry?.path();
+
+      if path.extension() != Some(OsStr::new("toml"))
+        || !path
+          .file_stem()
+          .and_then(OsStr::to_str)
+          .is_some_and(|stem| stem.starts_with("journal-"))
+      {
+        continue;
+      }
+
+      let file = match OpenOptions::new().append(true).read(true).open(&path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+        Err(error) => return Err(error.into()),
+      };
+
+      match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => continue,
+        Err(TryLockError::Error(error)) => return Err(error.into()),
+      }
+
+      // A run removes its journal as it finishes, before unlocking it, so one
+      // opened before then has been completed rather than interrupted.
+      if Lock::current(&file, &path) {
+        journals.push(Self {
+          error: None,
+          file,
+          path,
+        });
+      }
+    }
+
+    journals.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(journals)
+  }
+
+  /// Reads the steps recorded in this journal, and how many were started.
+  fn read(&mut self) -> Result<(Vec<Step>, usize)> {
+    let mut text = String::new();
+
+    self.file.read_to_string(&mut text)?;
+
+    let table = text
+      .parse::<Table>()
+      .map_err(|error| Self::error(&self.path, error.message()))?;
+
+    let steps = table
+      .get("step")
+      .and_then(Value::as_array)
+      .map_or(Some(Vec::new()), |steps| {
+        steps
+          .iter()
+          .map(|step| step.as_table().and_then(Step::parse))
+          .collect::<Option<Vec<Step>>>()
+      })
+      .ok_or_else(|| Self::error(&self.path, "found a malformed step"))?;
+
+    let started = table
+      .get("started")
+      .and_then(Value::as_array)
+      .map_or(0, Vec::len);
+
+    Ok((steps, started))
+  }
+
+  /// Makes the steps of this journal which weren't made before its run was
+  /// interrupted, then removes it, returning how many were made.
+  fn replay(mut self) -> Result<usize> {
+    let (steps, started) = self.read()?;
+
+    let mut filesystem = Real::default();
+
+    let mut resumed = 0;
+
+    for (index, step) in
+      steps.iter().enumerate().skip(started.saturating_sub(1))
+    {
+      if index + 1 == started {
+        if step.done() {
+          continue;
+        }
+      } else {
+        self.begin();
+      }
+
+      step.perform(&mut filesystem)?;
+
+      println!("{step}");
+
+      resumed += 1;
+    }
+
+    self.finish()?;
+
+    Ok(resumed)
+  }
+
+  /// Makes the rest of the steps of every run that was interrupted, then
+  /// removes their journals.
+  pub(crate) fn resume() -> Result {
+    let Some(directory) = Self::directory() else {
+      return Err(Self::error(
+        Path::new(env!("CARGO_PKG_NAME")),
+        "neither XDG_STATE_HOME nor HOME is set",
+      ));
+    };
+
+    let journals = Self::interrupted(&directory)?;
+
+    if journals.is_empty() {
+      return Err(Self::error(
+        &directory,
+        "there is no interrupted run to resume",
+      ));
+    }
+
+    let mut resumed = 0;
+
+    for journal in journals {
+      resumed += journal.replay()?;
+    }
+
+    println!("{resumed} step(s) resumed");
+
+    Ok(())
+  }
+
+  /// Writes `steps` to a journal for this run, if there is a directory for
+  /// it and every path in them can be recorded, before any of them are made.
+  /// Refuses to start while the journal of an earlier run that was
+  /// interrupted and touched the same paths is still around.
+  pub(crate) fn write(steps: &[Step]) -> Result<Option<Self>> {
+    Self::check(
+      &steps
+        .iter()
+        .flat_map(Step::paths)
+        .map(Path::to_owned)
+        .collect(),
+    )?;
+
+    let Some(directory) = Self::directory() else {
+      return Ok(None);
+    };
+
+    let path = directory.join(format!("journal-{}.toml", process::id()));
+
+    let steps = steps
+      .iter()
+      .map(|step| step.table().map(Value::Table))
+      .collect::<Option<Vec<Value>>>();
+
+    let Some(steps) = steps else {
+      eprintln!(
+        "warning: Not writing journal `{}`, a path isn't valid UTF-8",
+        path.display()
+      );
+      return Ok(None);
+    };
+
+    fs::create_dir_all(&directory)?;
+
+    let mut table = Table::new();
+
+    table.insert("step".to_owned(), Value::Array(steps));
+
+    // Locked and filled in under a temporary name, so that no other run sees
+    // the journal before it is complete and mistakes it for an interrupted one.
+    let mut file = NamedTempFile::new_in(&directory)?;
+
+    file.as_file().lock()?;
+
+    file.write_all(table.to_string().as_bytes())?;
+
+    let file = file.persist_noclobber(&path).map_err(|error| error.error)?;
+
+    Ok(Some(Self {
+      error: None,
+      file,
+      path,
+    }))
+  }
+}
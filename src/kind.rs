@@ -0,0 +1,6 @@
+/// The kind of a filesystem entry.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+  Directory,
+  File,
+}
@@ -0,0 +1,110 @@
+//! Bulk rename files using your favorite editor.
+//!
+//! The [`Plan`] type validates and executes a set of renames against any
+//! [`Filesystem`], either the [`Real`] one on disk or an in-memory [`Memory`]
+//! filesystem.
+
+mod arguments;
+mod buffer;
+mod column;
+mod companions;
+mod config;
+mod document;
+mod entry_type;
+mod error;
+mod event;
+mod filesystem;
+mod filter;
+mod format;
+#[cfg(any(test, fuzzing))]
+pub mod fuzz;
+mod hook;
+mod ignore;
+mod input;
+mod intermediate;
+mod journal;
+mod kind;
+mod link;
+mod lock;
+mod memory;
+mod metadata;
+mod plan;
+mod policy;
+mod problem;
+mod progress;
+mod progress_style;
+mod prompt;
+mod real;
+mod reference;
+mod scope;
+mod screen;
+mod sort;
+mod step;
+mod subcommand;
+mod tree;
+mod tui;
+
+pub use crate::{
+  error::Error, event::Event, filesystem::Filesystem, kind::Kind,
+  memory::Memory, metadata::Metadata, plan::Plan, problem::Problem, real::Real,
+};
+
+use {
+  crate::{
+    arguments::Arguments, buffer::Buffer, column::Column,
+    companions::Companions, config::Config, document::Document,
+    entry_type::EntryType, filter::Filter, format::Format, hook::Hook,
+    ignore::Ignore, input::Input, intermediate::Intermediate, journal::Journal,
+    link::Link, lock::Lock, policy::Policy, progress::Progress,
+    progress_style::ProgressStyle, prompt::Prompt, reference::Reference,
+    scope::Scope, screen::Screen, sort::Sort, step::Step,
+    subcommand::Subcommand, tree::Tree, tui::Tui,
+  },
+  clap::{
+    error::ErrorKind, parser::ValueSource, Arg, ArgAction, ArgMatches,
+    CommandFactory, FromArgMatches, Parser, ValueEnum,
+  },
+  clap_complete::Shell,
+  crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{
+      Event as TerminalEvent, KeyCode, KeyEvent, KeyEventKind, KeyModifiers,
+    },
+    execute, queue,
+    style::{
+      Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor,
+    },
+    terminal::{
+      self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen,
+    },
+  },
+  glob::{glob, MatchOptions, Pattern, PatternError},
+  path_absolutize::*,
+  std::{
+    cmp::Ordering,
+    collections::{btree_map, BTreeMap, BTreeSet, HashMap, HashSet},
+    env,
+    ffi::{OsStr, OsString},
+    fmt::{self, Display, Formatter},
+    fs::{self, File, OpenOptions, TryLockError},
+    io::{self, IsTerminal, Read, Write},
+    iter, mem,
+    ops::Range,
+    path::{is_separator, Component, Path, PathBuf},
+    process::{self, Command},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+  },
+  tempfile::{Builder, NamedTempFile, TempDir},
+  toml::{Table, Value},
+};
+
+/// A result whose error defaults to [`Error`].
+pub type Result<T = (), E = Error> = std::result::Result<T, E>;
+
+/// Runs the command-line interface, exiting the process on failure.
+pub fn run() {
+  if let Err(error) = Arguments::load().and_then(Arguments::run) {
+    eprintln!("error: {error}");
+    process::exit(error.code());
+  }
+}
@@ -0,0 +1,139 @@
+use super::*;
+
+/// A symbolic link whose target is rewritten once the renames are made, so
+/// that it still resolves to the same path.
+#[derive(Debug)]
+pub(crate) struct Link {
+  destination: PathBuf,
+  path: PathBuf,
+  replacement: PathBuf,
+  target: PathBuf,
+}
+
+impl Link {
+  /// Replaces the link, wherever it ends up after renaming, with one
+  /// pointing at the rewritten target.
+  pub(crate) fn apply(&self) -> Result {
+    if fs::read_link(&self.destination)? != self.target {
+      return Err(Error::ChangedLink {
+        path: self.path.clone(),
+      });
+    }
+
+    fs::remove_file(&self.destination)?;
+
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&self.replacement, &self.destination)?;
+
+    #[cfg(windows)]
+    {
+      let resolved = self.destination.parent().map_or_else(
+        || self.replacement.clone(),
+        |parent| parent.join(&self.replacement),
+      );
+
+      if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(
+          &self.replacement,
+          &self.destination,
+        )?;
+      } else {
+        std::os::windows::fs::symlink_file(
+          &self.replacement,
+          &self.destination,
+        )?;
+      }
+    }
+
+    Ok(())
+  }
+
+  pub(crate) fn destination(&self) -> &Path {
+    &self.destination
+  }
+
+  /// Adds every symbolic link at or beneath `path` to `links`, without
+  /// following them.
+  pub(crate) fn find(path: &Path, links: &mut BTreeSet<PathBuf>) -> Result {
+    let metadata = fs::symlink_metadata(path)?;
+
+    if metadata.is_symlink() {
+      links.insert(path.to_owned());
+    } else if metadata.is_dir() {
+      for entry in fs::read_dir(path)? {
+        Self::find(&entry?.path(), links)?;
+      }
+    }
+
+    Ok(())
+  }
+
+  pub(crate) fn new(
+    destination: PathBuf,
+    path: PathBuf,
+    replacement: PathBuf,
+    target: PathBuf,
+  ) -> Self {
+    Self {
+      destination,
+      path,
+      replacement,
+      target,
+    }
+  }
+
+  pub(crate) fn path(&self) -> &Path {
+    &self.path
+  }
+
+  pub(crate) fn replacement(&self) -> &Path {
+    &self.replacement
+  }
+
+  /// Reads the link at `path` and, if `moves` change what it resolves to,
+  /// either by moving the link or its target, works out the target that
+  /// keeps it pointing at the same path. Relative targets stay relative and
+  /// absolute ones stay absolute.
+  pub(crate) fn scan(
+    path: PathBuf,
+    moves: &[(PathBuf, PathBuf)],
+  ) -> Result<Option<Self>> {
+    let target = fs::read_link(&path)?;
+
+    let absolute = path.absolutize()?.into_owned();
+
+    let destination =
+      Document::moved(&absolute, moves).unwrap_or_else(|| absolute.clone());
+
+    let (Some(directory), Some(base)) =
+      (absolute.parent(), destination.parent())
+    else {
+      return Ok(None);
+    };
+
+    let resolved = target.absolutize_from(directory)?.into_owned();
+
+    let moved = Document::moved(&resolved, moves).unwrap_or(resolved);
+
+    if target.absolutize_from(base)? == moved {
+      return Ok(None);
+    }
+
+    let replacement = if target.is_absolute() {
+      moved
+    } else {
+      PathBuf::from(Document::relative(&moved, base))
+    };
+
+    Ok(Some(Self {
+      destination,
+      path,
+      replacement,
+      target,
+    }))
+  }
+
+  pub(crate) fn target(&self) -> &Path {
+    &self.target
+  }
+}
@@ -0,0 +1,145 @@
+use super::*;
+
+/// An advisory lock on a directory, held through a `.edmv.lock` file inside
+/// it which records the holder's PID, so that concurrent runs don't rename
+/// paths out from under each other.
+pub(crate) struct Lock {
+  file: File,
+  path: PathBuf,
+}
+
+impl Lock {
+  pub(crate) const NAME: &str = ".edmv.lock";
+
+  /// Locks `directory`, waiting for any other run holding it if `wait`, or
+  /// failing with the holder's PID otherwise. Returns `None` if no lock file
+  /// can be created there, because the directory doesn't exist or can't be
+  /// written to, in which case nothing in it can be renamed either.
+  pub(crate) fn acquire(directory: &Path, wait: bool) -> Result<Option<Self>> {
+    let path = directory.join(Self::NAME);
+
+    loop {
+      let mut file = match OpenOptions::new()
+        .create(true)
+        .read(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+      {
+        Ok(file) => file,
+        Err(error)
+          if matches!(
+            error.kind(),
+            io::ErrorKind::NotFound
+              | io::ErrorKind::PermissionDenied
+              | io::ErrorKind::ReadOnlyFilesystem
+          ) =>
+        {
+          return Ok(None);
+        }
+        Err(error) => return Err(error.into()),
+      };
+
+      if wait {
+        file.lock()?;
+      } else {
+        match file.try_lock() {
+          Ok(()) => {}
+          Err(TryLockError::WouldBlock) => {
+            return Err(Self::locked(directory, &mut file));
+          }
+          Err(TryLockError::Error(error)) => return Err(error.into()),
+        }
+      }
+
+      // The previous holder removes the file as it finishes, so a run which
+      // opened it before then has locked a file no other run will see.
+      if !Self::current(&file, &path) {
+        continue;
+      }
+
+      file.set_len(0)?;
+
+      write!(file, "{}", process::id())?;
+
+      return Ok(Some(Self { file, path }));
+    }
+  }
+
+  /// Fails with the holder's PID, or waits for it if `wait`, while another
+  /// run holds the lock on `directory`, without taking the lock itself.
+  pub(crate) fn check(directory: &Path, wait: bool) -> Result {
+    let mut file = match File::open(directory.join(Self::NAME)) {
+      Ok(file) => file,
+      Err(error)
+        if matches!(
+          error.kind(),
+          io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied
+        ) =>
+      {
+        return Ok(());
+      }
+      Err(error) => return Err(error.into()),
+    };
+
+    if wait {
+      file.lock_shared()?;
+      return Ok(());
+    }
+
+    match file.try_lock_shared() {
+      Ok(()) => Ok(()),
+      Err(TryLockError::WouldBlock) => Err(Self::locked(directory, &mut file)),
+      Err(TryLockError::Error(error)) => Err(error.into()),
+    }
+  }
+
+  /// Whether `file` is still the one linked at `path`.
+  #[cfg(unix)]
+  pub(crate) fn current(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (file.metadata(), fs::metadata(path)) {
+      (Ok(open), Ok(linked)) => {
+        open.dev() == linked.dev() && open.ino() == linked.ino()
+      }
+      _ => false,
+    }
+  }
+
+  #[cfg(windows)]
+  pub(crate) fn current(_file: &File, _path: &Path) -> bool {
+    true
+  }
+
+  fn locked(directory: &Path, file: &mut File) -> Error {
+    let mut holder = String::new();
+
+    file.read_to_string(&mut holder).ok();
+
+    Error::Locked {
+      directory: directory.to_owned(),
+      holder: holder.trim().to_owned(),
+    }
+  }
+
+  /// Follows a rename of `from` to `to`, made while the lock is held, which
+  /// moved the locked directory, so that the lock file is removed from where
+  /// it ended up rather than where it was created.
+  pub(crate) fn moved(&mut self, from: &Path, to: &Path) {
+    if let Ok(rest) = self.path.strip_prefix(from) {
+      self.path = to.join(rest);
+    }
+  }
+}
+
+impl Drop for Lock {
+  fn drop(&mut self) {
+    // Removed while still locked, so that no other run locks it in between
+    // and then loses it. Windows won't remove a file that is still open, so
+    // there the lock file is left behind.
+    fs::remove_file(&self.path).ok();
+
+    self.file.unlock().ok();
+  }
+}
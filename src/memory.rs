@@ -0,0 +1,377 @@
+use super::*;
+
+#[derive(Clone, Debug)]
+enum Node {
+  Present {
+    created: bool,
+    metadata: Metadata,
+    origin: Option<PathBuf>,
+  },
+  Removed,
+}
+
+#[derive(Debug)]
+struct Entry {
+  created: bool,
+  metadata: Metadata,
+  origin: Option<PathBuf>,
+}
+
+/// An in-memory filesystem.
+///
+/// It starts out either empty, or as a copy-on-write overlay of another
+/// filesystem which is read lazily and never modified. Renames follow POSIX
+/// semantics, and additionally fail when they would replace a path created by
+/// an earlier rename.
+pub struct Memory<'a> {
+  base: Option<&'a dyn Filesystem>,
+  inodes: u64,
+  nodes: BTreeMap<PathBuf, Node>,
+  temporaries: usize,
+}
+
+impl Filesystem for Memory<'_> {
+  fn create(&mut self, path: &Path, kind: Kind) -> io::Result<()> {
+    let path = Self::absolute(path)?;
+
+    let parent = self.parent(&path)?;
+
+    if parent.metadata.readonly {
+      return Err(io::ErrorKind::PermissionDenied.into());
+    }
+
+    if self.lookup(&path).is_some() {
+      return Err(io::ErrorKind::AlreadyExists.into());
+    }
+
+    self.inodes += 1;
+
+    self.nodes.insert(
+      path,
+      Node::Present {
+        created: true,
+        metadata: Metadata {
+          device: parent.metadata.device,
+          inode: Some(self.inodes),
+          kind,
+          readonly: false,
+        },
+        origin: None,
+      },
+    );
+
+    Ok(())
+  }
+
+  fn metadata(&self, path: &Path) -> Option<Metadata> {
+    Self::absolute(path)
+      .ok()
+      .and_then(|path| self.lookup(&path))
+      .map(|entry| entry.metadata)
+  }
+
+  fn read_dir(&self, path: &Path) -> io::Result<Vec<OsString>> {
+    let path = Self::absolute(path)?;
+
+    let entry = self.lookup(&path).ok_or_else(Self::not_found)?;
+
+    if entry.metadata.kind != Kind::Directory {
+      return Err(io::ErrorKind::NotADirectory.into());
+    }
+
+    let mut names = BTreeSet::new();
+
+    if let (Some(base), Some(origin)) = (self.base, &entry.origin) {
+      for name in base.read_dir(origin)? {
+        if self.lookup(&path.join(&name)).is_some() {
+          names.insert(name);
+        }
+      }
+    }
+
+    for (key, node) in &self.nodes {
+      if key.parent() == Some(&path) && matches!(node, Node::Present { .. }) {
+        names.extend(key.file_name().map(OsStr::to_os_string));
+      }
+    }
+
+    Ok(names.into_iter().collect())
+  }
+
+  fn rename(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+    let (from, to) = (Self::absolute(from)?, Self::absolute(to)?);
+
+    let source = self.lookup(&from).ok_or_else(Self::not_found)?;
+
+    for path in [&from, &to] {
+      let parent = self.parent(path)?;
+
+      if parent.metadata.readonly {
+        return Err(io::ErrorKind::PermissionDenied.into());
+      }
+    }
+
+    if from == to {
+      return Ok(());
+    }
+
+    if let (Some(source), Some(destination)) =
+      (source.metadata.device, self.parent(&to)?.metadata.device)
+    {
+      if source != destination {
+        return Err(io::ErrorKind::CrossesDevices.into());
+      }
+    }
+
+    if source.metadata.kind == Kind::Directory && to.starts_with(&from) {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "cannot move a directory into itself",
+      ));
+    }
+
+    if let Some(destination) = self.lookup(&to) {
+      if destination.created {
+        return Err(io::Error::new(
+          io::ErrorKind::AlreadyExists,
+          "destination was created by an earlier rename",
+        ));
+      }
+
+      match (source.metadata.kind, destination.metadata.kind) {
+        (Kind::File, Kind::Directory) => {
+          return Err(io::ErrorKind::IsADirectory.into());
+        }
+        (Kind::Directory, Kind::File) => {
+          return Err(io::ErrorKind::NotADirectory.into());
+        }
+        (Kind::Directory, Kind::Directory)
+          if !self.read_dir(&to)?.is_empty() =>
+        {
+          return Err(io::ErrorKind::DirectoryNotEmpty.into());
+        }
+        _ => {}
+      }
+    }
+
+    self.nodes.retain(|key, _| !key.starts_with(&to));
+
+    let moved = self
+      .nodes
+      .extract_if(.., |key, _| key.starts_with(&from))
+      .filter_map(|(key, node)| {
+        key
+          .strip_prefix(&from)
+          .ok()
+          .filter(|suffix| !suffix.as_os_str().is_empty())
+          .map(|suffix| (to.join(suffix), node))
+      })
+      .collect::<Vec<_>>();
+
+    self.nodes.extend(moved);
+
+    self.nodes.insert(
+      to,
+      Node::Present {
+        created: true,
+        metadata: source.metadata,
+        origin: source.origin,
+      },
+    );
+
+    self.nodes.insert(from, Node::Removed);
+
+    Ok(())
+  }
+
+  fn temporary(&mut self, kind: Kind) -> io::Result<PathBuf> {
+    let directory = Self::absolute(&env::temp_dir())?;
+
+    self.create_dir_all(&directory)?;
+
+    let device = self
+      .lookup(&directory)
+      .and_then(|entry| entry.metadata.device);
+
+    let path = loop {
+      let path = directory.join(format!(".edmv-{}", self.temporaries));
+
+      self.temporaries += 1;
+
+      if self.lookup(&path).is_none() {
+        break path;
+      }
+    };
+
+    self.insert(&path, kind, device);
+
+    Ok(path)
+  }
+}
+
+impl Default for Memory<'_> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<'a> Memory<'a> {
+  fn absolute(path: &Path) -> io::Result<PathBuf> {
+    Ok(path.absolutize()?.to_path_buf())
+  }
+
+  /// Creates the directory `path`, along with any missing parents.
+  ///
+  /// # Errors
+  ///
+  /// Returns an error if `path` or one of its parents is a file.
+  pub fn create_dir_all(&mut self, path: &Path) -> io::Result<()> {
+    let path = Self::absolute(path)?;
+
+    for ancestor in path.ancestors().collect::<Vec<_>>().into_iter().rev() {
+      match self.lookup(ancestor) {
+        Some(entry) if entry.metadata.kind == Kind::Directory => {}
+        Some(_) => return Err(io::ErrorKind::NotADirectory.into()),
+        None => self.insert(ancestor, Kind::Directory, None),
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Creates an empty file at `path`, along with any missing parents.
+  ///
+  /// # Errors
+  ///
+  /// Returns an error if one of the parents of `path` is a file, or if `path`
+  /// is a directory.
+  pub fn create_file(&mut self, path: &Path) -> io::Result<()> {
+    let path = Self::absolute(path)?;
+
+    if let Some(parent) = path.parent() {
+      self.create_dir_all(parent)?;
+    }
+
+    if self.is_dir(&path) {
+      return Err(io::ErrorKind::IsADirectory.into());
+    }
+
+    self.insert(&path, Kind::File, None);
+
+    Ok(())
+  }
+
+  fn insert(&mut self, path: &Path, kind: Kind, device: Option<u64>) {
+    self.inodes += 1;
+
+    self.nodes.insert(
+      path.to_path_buf(),
+      Node::Present {
+        created: false,
+        metadata: Metadata {
+          device,
+          inode: Some(self.inodes),
+          kind,
+          readonly: false,
+        },
+        origin: None,
+      },
+    );
+  }
+
+  fn lookup(&self, path: &Path) -> Option<Entry> {
+    for ancestor in path.ancestors() {
+      let Some(node) = self.nodes.get(ancestor) else {
+        continue;
+      };
+
+      return match node {
+        Node::Present {
+          created,
+          metadata,
+          origin,
+        } if ancestor == path => Some(Entry {
+          created: *created,
+          metadata: *metadata,
+          origin: origin.clone(),
+        }),
+        Node::Present {
+          metadata:
+            Metadata {
+              kind: Kind::Directory,
+              ..
+            },
+          origin: Some(origin),
+          ..
+        } => self.real(&origin.join(path.strip_prefix(ancestor).ok()?)),
+        Node::Present { .. } | Node::Removed => None,
+      };
+    }
+
+    match self.base {
+      Some(_) => self.real(path),
+      None => path.parent().is_none().then_some(Entry {
+        created: false,
+        metadata: Metadata {
+          device: None,
+          inode: None,
+          kind: Kind::Directory,
+          readonly: false,
+        },
+        origin: None,
+      }),
+    }
+  }
+
+  /// Creates an empty in-memory filesystem.
+  #[must_use]
+  pub fn new() -> Self {
+    Self {
+      base: None,
+      inodes: 0,
+      nodes: BTreeMap::new(),
+      temporaries: 0,
+    }
+  }
+
+  fn not_found() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "no such file or directory")
+  }
+
+  /// Creates an in-memory overlay of `base`.
+  ///
+  /// Paths are read from `base` until they are renamed, after which all
+  /// changes are kept in memory.
+  #[must_use]
+  pub fn overlay(base: &'a dyn Filesystem) -> Self {
+    Self {
+      base: Some(base),
+      ..Self::new()
+    }
+  }
+
+  fn parent(&self, path: &Path) -> io::Result<Entry> {
+    match self.lookup(path.parent().unwrap_or(path)) {
+      Some(
+        entry @ Entry {
+          metadata:
+            Metadata {
+              kind: Kind::Directory,
+              ..
+            },
+          ..
+        },
+      ) => Ok(entry),
+      Some(_) => Err(io::ErrorKind::NotADirectory.into()),
+      None => Err(Self::not_found()),
+    }
+  }
+
+  fn real(&self, path: &Path) -> Option<Entry> {
+    self.base?.metadata(path).map(|metadata| Entry {
+      created: false,
+      metadata,
+      origin: Some(path.to_path_buf()),
+    })
+  }
+}
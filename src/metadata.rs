@@ -0,0 +1,34 @@
+use super::*;
+
+/// The subset of filesystem metadata edmv inspects.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Metadata {
+  pub device: Option<u64>,
+  pub inode: Option<u64>,
+  pub kind: Kind,
+  pub readonly: bool,
+}
+
+impl From<fs::Metadata> for Metadata {
+  fn from(metadata: fs::Metadata) -> Self {
+    #[cfg(unix)]
+    let (device, inode) = {
+      use std::os::unix::fs::MetadataExt;
+      (Some(metadata.dev()), Some(metadata.ino()))
+    };
+
+    #[cfg(not(unix))]
+    let (device, inode) = (None, None);
+
+    Self {
+      device,
+      inode,
+      kind: if metadata.is_dir() {
+        Kind::Directory
+      } else {
+        Kind::File
+      },
+      readonly: metadata.permissions().readonly(),
+    }
+  }
+}
@@ -0,0 +1,387 @@
+use super::*;
+
+/// A set of `(source, destination)` renames, along with any new paths to
+/// create before renaming.
+#[derive(Debug)]
+pub struct Plan {
+  creations: Vec<(PathBuf, Kind)>,
+  pairs: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Plan {
+  fn counts(&self) -> HashMap<&PathBuf, usize> {
+    self
+      .pairs
+      .iter()
+      .map(|(_, destination)| destination)
+      .chain(self.creations.iter().map(|(path, _)| path))
+      .fold(HashMap::new(), |mut counts, path| {
+        *counts.entry(path).or_insert(0) += 1;
+        counts
+      })
+  }
+
+  /// Returns the problems with each new path, in the same order as
+  /// [`Plan::creations`]. Existing paths are never replaced, even with
+  /// `force`.
+  ///
+  /// # Errors
+  ///
+  /// Returns an error if a path cannot be made absolute.
+  pub fn creation_problems(
+    &self,
+    filesystem: &dyn Filesystem,
+  ) -> Result<Vec<Vec<Problem>>> {
+    let counts = self.counts();
+
+    let directories = self.directories()?;
+
+    let mut problems = Vec::new();
+
+    for (path, _) in &self.creations {
+      let mut found = Vec::new();
+
+      if counts[path] > 1 {
+        found.push(Problem::Duplicate);
+      }
+
+      if filesystem.exists(path) {
+        found.push(Problem::Existing);
+      }
+
+      if Self::orphaned(path, filesystem, &directories)? {
+        found.push(Problem::MissingParent);
+      }
+
+      problems.push(found);
+    }
+
+    Ok(problems)
+  }
+
+  /// Returns the new paths in this plan, which are created in order before
+  /// anything is renamed.
+  #[must_use]
+  pub fn creations(&self) -> &[(PathBuf, Kind)] {
+    &self.creations
+  }
+
+  fn directories(&self) -> Result<HashSet<PathBuf>> {
+    self
+      .creations
+      .iter()
+      .filter(|(_, kind)| *kind == Kind::Directory)
+      .map(|(path, _)| Ok(path.absolutize()?.into_owned()))
+      .collect()
+  }
+
+  /// Executes the plan against `filesystem`, reporting each step to
+  /// `observe`, and returns the number of paths created or renamed.
+  ///
+  /// This is [`Plan::perform`] with the stages returned by [`Plan::stages`].
+  ///
+  /// # Errors
+  ///
+  /// Returns an error if a temporary path cannot be created or a rename fails,
+  /// in which case earlier renames are not undone.
+  pub fn execute(
+    &self,
+    filesystem: &mut dyn Filesystem,
+    resolve: bool,
+    observe: impl FnMut(Event),
+  ) -> Result<usize> {
+    let stages = self.stages(filesystem, resolve)?;
+
+    self.perform(filesystem, &stages, observe)
+  }
+
+  /// Creates a plan renaming each source to its destination.
+  #[must_use]
+  pub fn new(pairs: Vec<(PathBuf, PathBuf)>) -> Self {
+    Self {
+      creations: Vec::new(),
+      pairs,
+    }
+  }
+
+  fn orphaned(
+    path: &Path,
+    filesystem: &dyn Filesystem,
+    directories: &HashSet<PathBuf>,
+  ) -> Result<bool> {
+    Ok(path.absolutize()?.parent().is_some_and(|parent| {
+      !filesystem.exists(parent) && !directories.contains(parent)
+    }))
+  }
+
+  /// Returns the renames in this plan.
+  #[must_use]
+  pub fn pairs(&self) -> &[(PathBuf, PathBuf)] {
+    &self.pairs
+  }
+
+  /// Creates the new paths, then makes the rename calls in `stages`, which
+  /// must have been returned by [`Plan::stages`] for this plan, reporting
+  /// each step to `observe`. Returns the number of paths created or renamed.
+  ///
+  /// # Errors
+  ///
+  /// Returns an error if a creation or rename fails, in which case earlier
+  /// steps are not undone.
+  pub fn perform(
+    &self,
+    filesystem: &mut dyn Filesystem,
+    stages: &[Vec<(PathBuf, PathBuf)>],
+    mut observe: impl FnMut(Event),
+  ) -> Result<usize> {
+    for (path, kind) in &self.creations {
+      observe(Event::Create { kind: *kind, path });
+
+      filesystem
+        .create(path, *kind)
+        .map_err(|source| Error::Create {
+          path: path.clone(),
+          source,
+        })?;
+
+      observe(Event::Created { kind: *kind, path });
+    }
+
+    let first = stages.first().cloned().unwrap_or_default();
+
+    let mut renamed = 0;
+
+    for (i, stage) in stages.iter().enumerate() {
+      observe(Event::Stage {
+        index: i,
+        stages: stages.len(),
+      });
+
+      for (j, (source, destination)) in stage.iter().enumerate() {
+        observe(Event::Rename {
+          from: source,
+          to: destination,
+        });
+
+        filesystem.rename(source, destination).map_err(|error| {
+          Error::Rename {
+            from: source.clone(),
+            source: error,
+            to: destination.clone(),
+          }
+        })?;
+
+        if i == stages.len() - 1 && j < first.len() {
+          observe(Event::Renamed {
+            destination,
+            source: &first[j].0,
+          });
+
+          renamed += 1;
+        }
+      }
+    }
+
+    Ok(self.creations.len() + renamed)
+  }
+
+  /// Returns the problems with each rename, in the same order as
+  /// [`Plan::pairs`], ignoring existing destinations with `force` and
+  /// conflicting renames with `resolve`.
+  ///
+  /// # Errors
+  ///
+  /// Returns an error if a destination cannot be made absolute.
+  pub fn problems(
+    &self,
+    filesystem: &dyn Filesystem,
+    force: bool,
+    resolve: bool,
+  ) -> Result<Vec<Vec<Problem>>> {
+    let sources = self
+      .pairs
+      .iter()
+      .map(|(source, _)| source)
+      .collect::<HashSet<&PathBuf>>();
+
+    let counts = self.counts();
+
+    let directories = self.directories()?;
+
+    let mut problems = Vec::new();
+
+    for (source, destination) in &self.pairs {
+      let mut found = Vec::new();
+
+      if counts[destination] > 1 {
+        found.push(Problem::Duplicate);
+      }
+
+      if !force && filesystem.exists(destination) {
+        found.push(Problem::Existing);
+      }
+
+      if !resolve && sources.contains(destination) {
+        found.push(Problem::Conflicting);
+      }
+
+      if filesystem.is_dir(source) && filesystem.is_file(destination) {
+        found.push(Problem::DirectoryToFile);
+      }
+
+      if Self::orphaned(destination, filesystem, &directories)? {
+        found.push(Problem::MissingParent);
+      }
+
+      problems.push(found);
+    }
+
+    Ok(problems)
+  }
+
+  /// Returns the rename calls that carry out the plan, in stages made one
+  /// after another.
+  ///
+  /// A source whose destination is an existing directory, which is not itself
+  /// being renamed, is moved into that directory. With `resolve`, every
+  /// source is first moved to a temporary path reserved on `filesystem`, and
+  /// only then to its destination, so that renames may overlap or form
+  /// cycles.
+  ///
+  /// # Errors
+  ///
+  /// Returns an error if a temporary path cannot be created.
+  pub fn stages(
+    &self,
+    filesystem: &mut dyn Filesystem,
+    resolve: bool,
+  ) -> Result<Vec<Vec<(PathBuf, PathBuf)>>> {
+    let targets = self.targets(filesystem);
+
+    let input = self
+      .pairs
+      .iter()
+      .zip(targets)
+      .map(|((source, _), destination)| {
+        Ok(if resolve {
+          let kind = if filesystem.is_file(source) {
+            Kind::File
+          } else {
+            Kind::Directory
+          };
+
+          vec![source.clone(), filesystem.temporary(kind)?, destination]
+        } else {
+          vec![source.clone(), destination]
+        })
+      })
+      .collect::<Result<Vec<Vec<PathBuf>>>>()?;
+
+    Ok(
+      (0..input.iter().map(|inner| inner.len() - 1).min().unwrap_or(0))
+        .map(|i| {
+          input
+            .iter()
+            .filter_map(|inner| inner.windows(2).nth(i))
+            .map(|chunk| (chunk[0].clone(), chunk[1].clone()))
+            .collect()
+        })
+        .collect(),
+    )
+  }
+
+  /// Returns the path each source ends up at, in the same order as
+  /// [`Plan::pairs`].
+  ///
+  /// This is its destination, unless that is an existing directory which is
+  /// not itself being renamed, in which case the source is moved into it.
+  #[must_use]
+  pub fn targets(&self, filesystem: &dyn Filesystem) -> Vec<PathBuf> {
+    let sources = self
+      .pairs
+      .iter()
+      .map(|(source, _)| source)
+      .collect::<HashSet<&PathBuf>>();
+
+    self
+      .pairs
+      .iter()
+      .map(|(source, destination)| match source.file_name() {
+        Some(name)
+          if filesystem.is_dir(destination)
+            && !sources.contains(destination) =>
+        {
+          destination.join(name)
+        }
+        _ => destination.clone(),
+      })
+      .collect()
+  }
+
+  /// Checks the plan against `filesystem` before anything is renamed.
+  ///
+  /// # Errors
+  ///
+  /// Returns every problem found, grouped by kind, unless `force` allows
+  /// replacing existing destinations or `resolve` allows conflicting renames.
+  pub fn validate(
+    &self,
+    filesystem: &dyn Filesystem,
+    force: bool,
+    resolve: bool,
+  ) -> Result {
+    let found = self.problems(filesystem, force, resolve)?;
+
+    let created = self.creation_problems(filesystem)?;
+
+    let mut problems = Vec::new();
+
+    for problem in [
+      Problem::Duplicate,
+      Problem::Existing,
+      Problem::Conflicting,
+      Problem::DirectoryToFile,
+      Problem::MissingParent,
+    ] {
+      let mut paths = self
+        .pairs
+        .iter()
+        .zip(&found)
+        .filter(|(_, found)| found.contains(&problem))
+        .map(|((source, destination), _)| problem.describe(source, destination))
+        .chain(
+          self
+            .creations
+            .iter()
+            .zip(&created)
+            .filter(|(_, found)| found.contains(&problem))
+            .map(|((path, _), _)| problem.describe(path, path)),
+        )
+        .collect::<Vec<String>>();
+
+      if paths.is_empty() {
+        continue;
+      }
+
+      paths.sort();
+
+      if problem == Problem::Duplicate {
+        paths.dedup();
+      }
+
+      problems.push(problem.error(paths));
+    }
+
+    match problems.len() {
+      0 => Ok(()),
+      1 => Err(problems.remove(0)),
+      _ => Err(Error::Validation { problems }),
+    }
+  }
+
+  /// Adds new paths to create, in order, before anything is renamed.
+  #[must_use]
+  pub fn with_creations(self, creations: Vec<(PathBuf, Kind)>) -> Self {
+    Self { creations, ..self }
+  }
+}
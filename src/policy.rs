@@ -0,0 +1,9 @@
+use super::*;
+
+/// What to do with a rename whose pre-hook fails.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub(crate) enum Policy {
+  #[default]
+  Abort,
+  Skip,
+}
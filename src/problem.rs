@@ -0,0 +1,52 @@
+use super::*;
+
+/// A problem with a single rename, found by [`Plan::problems`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Problem {
+  Conflicting,
+  DirectoryToFile,
+  Duplicate,
+  Existing,
+  MissingParent,
+}
+
+impl Display for Problem {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      Self::Conflicting => write!(f, "conflict"),
+      Self::DirectoryToFile => write!(f, "directory to file"),
+      Self::Duplicate => write!(f, "duplicate"),
+      Self::Existing => write!(f, "exists"),
+      Self::MissingParent => write!(f, "missing parent"),
+    }
+  }
+}
+
+impl Problem {
+  pub(crate) fn describe(self, source: &Path, destination: &Path) -> String {
+    match self {
+      Self::Conflicting | Self::DirectoryToFile => {
+        format!("{} -> {}", source.display(), destination.display())
+      }
+      Self::Duplicate | Self::Existing | Self::MissingParent => {
+        destination.display().to_string()
+      }
+    }
+  }
+
+  pub(crate) fn error(self, paths: Vec<String>) -> Error {
+    match self {
+      Self::Conflicting => Error::Conflicting { operations: paths },
+      Self::DirectoryToFile => Error::DirectoryToFile { operations: paths },
+      Self::Duplicate => Error::Duplicate {
+        destinations: paths,
+      },
+      Self::Existing => Error::Existing {
+        destinations: paths,
+      },
+      Self::MissingParent => Error::MissingParent {
+        destinations: paths,
+      },
+    }
+  }
+}
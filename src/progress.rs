@@ -0,0 +1,446 @@
+use super::*;
+
+/// Reports progress on stderr while a [`Plan`] executes, once it has run for
+/// longer than [`Progress::DELAY`], so that small batches print nothing.
+pub(crate) struct Progress {
+  current: PathBuf,
+  done: usize,
+  drawn: bool,
+  last: Option<Instant>,
+  now: Box<dyn Fn() -> Instant>,
+  pending: Vec<(bool, String)>,
+  start: Instant,
+  stderr: Box<dyn Write>,
+  stdout: Box<dyn Write>,
+  style: ProgressStyle,
+  total: usize,
+}
+
+impl Progress {
+  const BAR: usize = 20;
+
+  const DELAY: Duration = Duration::from_secs(1);
+
+  const INTERVAL: Duration = Duration::from_millis(100);
+
+  fn bar(&self, elapsed: Duration, width: usize) -> String {
+    let filled = (self.done * Self::BAR)
+      .checked_div(self.total)
+      .unwrap_or(Self::BAR)
+      .min(Self::BAR);
+
+    let status = format!(
+      "[{}{}] {}/{} {:.0}/s ETA {}",
+      "=".repeat(filled),
+      " ".repeat(Self::BAR - filled),
+      self.done,
+      self.total,
+      self.rate(elapsed),
+      Self::clock(self.remaining(elapsed)),
+    );
+
+    let room = width.saturating_sub(status.chars().count() + 1);
+
+    let path = self.current.display().to_string();
+
+    let count = path.chars().count();
+
+    if room == 0 {
+      status
+    } else if count <= room {
+      format!("{status} {path}")
+    } else {
+      format!(
+        "{status} …{}",
+        path.chars().skip(count + 1 - room).collect::<String>()
+      )
+    }
+  }
+
+  fn clock(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+
+    if seconds >= 3600 {
+      format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+      )
+    } else {
+      format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+  }
+
+  fn draw(&mut self, now: Instant) {
+    let elapsed = now - self.start;
+
+    match self.style {
+      ProgressStyle::Bar => {
+        let width = match terminal::size() {
+          Ok((width, _)) if width > 0 => width.into(),
+          _ => 80,
+        };
+
+        if !self.pending.is_empty() {
+          write!(self.stderr, "\r\x1b[K").ok();
+
+          for (stderr, line) in mem::take(&mut self.pending) {
+            self.emit(stderr, &line);
+          }
+        }
+
+        let bar = self.bar(elapsed, width);
+        write!(self.stderr, "\r{bar}\x1b[K").ok();
+        self.drawn = true;
+      }
+      ProgressStyle::Lines => {
+        let line = self.line(elapsed);
+        writeln!(self.stderr, "{line}").ok();
+      }
+      ProgressStyle::Auto | ProgressStyle::Never => return,
+    }
+
+    self.last = Some(now);
+  }
+
+  fn emit(&mut self, stderr: bool, line: &str) {
+    if stderr {
+      writeln!(self.stderr, "{line}").ok();
+    } else {
+      writeln!(self.stdout, "{line}").ok();
+    }
+  }
+
+  /// Prints `line` on stderr, like [`Progress::println`].
+  pub(crate) fn eprintln(&mut self, line: String) {
+    self.hold(true, line);
+  }
+
+  /// Reports the final count, if anything was reported before.
+  pub(crate) fn finish(&mut self) {
+    if self.last.is_none() {
+      return;
+    }
+
+    self.done = self.total;
+
+    self.draw((self.now)());
+
+    if self.drawn {
+      writeln!(self.stderr).ok();
+      self.drawn = false;
+    }
+  }
+
+  fn float(count: usize) -> f64 {
+    u32::try_from(count).map_or(f64::from(u32::MAX), f64::from)
+  }
+
+  fn hold(&mut self, stderr: bool, line: String) {
+    if !self.drawn {
+      self.emit(stderr, &line);
+      return;
+    }
+
+    self.pending.push((stderr, line));
+
+    let now = (self.now)();
+
+    if self.last.is_some_and(|last| now - last >= Self::INTERVAL) {
+      self.draw(now);
+    }
+  }
+
+  fn line(&self, elapsed: Duration) -> String {
+    format!(
+      "progress done={} total={} rate={:.1} eta={} path={}",
+      self.done,
+      self.total,
+      self.rate(elapsed),
+      self.remaining(elapsed).as_secs(),
+      self.current.display()
+    )
+  }
+
+  /// Creates a reporter for `total` creations and rename calls, resolving
+  /// [`ProgressStyle::Auto`] by whether stderr is a terminal.
+  pub(crate) fn new(style: ProgressStyle, total: usize) -> Self {
+    let style = match style {
+      ProgressStyle::Auto if io::stderr().is_terminal() => ProgressStyle::Bar,
+      ProgressStyle::Auto => ProgressStyle::Lines,
+      style => style,
+    };
+
+    Self {
+      current: PathBuf::new(),
+      done: 0,
+      drawn: false,
+      last: None,
+      now: Box::new(Instant::now),
+      pending: Vec::new(),
+      start: Instant::now(),
+      stderr: Box::new(io::stderr()),
+      stdout: Box::new(io::stdout()),
+      style,
+      total,
+    }
+  }
+
+  /// Counts a creation or rename call reported by [`Plan::execute`],
+  /// redrawing at most ten times a second as a bar, or once a second as
+  /// lines.
+  pub(crate) fn observe(&mut self, event: &Event) {
+    let (Event::Created { path, .. } | Event::Rename { from: path, .. }) =
+      *event
+    else {
+      return;
+    };
+
+    self.done += 1;
+    path.clone_into(&mut self.current);
+
+    let now = (self.now)();
+
+    if now - self.start < Self::DELAY {
+      return;
+    }
+
+    let interval = match self.style {
+      ProgressStyle::Bar => Self::INTERVAL,
+      _ => Duration::from_secs(1),
+    };
+
+    if self.last.is_none_or(|last| now - last >= interval) {
+      self.draw(now);
+    }
+  }
+
+  /// Prints `line` on stdout. While a bar is shown, lines are held back and
+  /// printed above it on its next throttled redraw, so that printing a line
+  /// per rename doesn't redraw the bar each time.
+  pub(crate) fn println(&mut self, line: String) {
+    self.hold(false, line);
+  }
+
+  fn rate(&self, elapsed: Duration) -> f64 {
+    let seconds = elapsed.as_secs_f64();
+
+    if seconds > 0.0 {
+      Self::float(self.done) / seconds
+    } else {
+      0.0
+    }
+  }
+
+  fn remaining(&self, elapsed: Duration) -> Duration {
+    let rate = self.rate(elapsed);
+
+    if rate > 0.0 {
+      Duration::from_secs_f64(
+        Self::float(self.total.saturating_sub(self.done)) / rate,
+      )
+    } else {
+      Duration::ZERO
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::*,
+    std::{cell::RefCell, rc::Rc},
+  };
+
+  #[derive(Clone, Default)]
+  struct Output(Rc<RefCell<Vec<u8>>>);
+
+  impl Output {
+    fn take(&self) -> String {
+      String::from_utf8(mem::take(&mut *self.0.borrow_mut())).unwrap()
+    }
+  }
+
+  impl Write for Output {
+    fn flush(&mut self) -> io::Result<()> {
+      Ok(())
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+      self.0.borrow_mut().write(buffer)
+    }
+  }
+
+  fn clocked(
+    style: ProgressStyle,
+    total: usize,
+  ) -> (Progress, Rc<RefCell<Instant>>, Output) {
+    let start = Instant::now();
+
+    let clock = Rc::new(RefCell::new(start));
+
+    let output = Output::default();
+
+    let progress = Progress {
+      now: Box::new({
+        let clock = clock.clone();
+        move || *clock.borrow()
+      }),
+      start,
+      stderr: Box::new(output.clone()),
+      stdout: Box::new(output.clone()),
+      style,
+      ..progress(0, total, "")
+    };
+
+    (progress, clock, output)
+  }
+
+  fn observe(progress: &mut Progress, path: &str) {
+    progress.observe(&Event::Rename {
+      from: Path::new(path),
+      to: Path::new(path),
+    });
+  }
+
+  fn progress(done: usize, total: usize, current: &str) -> Progress {
+    Progress {
+      current: PathBuf::from(current),
+      done,
+      drawn: false,
+      last: None,
+      now: Box::new(Instant::now),
+      pending: Vec::new(),
+      start: Instant::now(),
+      stderr: Box::new(io::sink()),
+      stdout: Box::new(io::sink()),
+      style: ProgressStyle::Lines,
+      total,
+    }
+  }
+
+  fn tick(clock: &RefCell<Instant>, millis: u64) {
+    *clock.borrow_mut() += Duration::from_millis(millis);
+  }
+
+  #[test]
+  fn bar() {
+    assert_eq!(
+      progress(50, 200, "a/b.txt").bar(Duration::from_secs(5), 80),
+      "[=====               ] 50/200 10/s ETA 0:15 a/b.txt"
+    );
+  }
+
+  #[test]
+  fn bar_holds_lines_until_redraw() {
+    let (mut progress, clock, output) = clocked(ProgressStyle::Bar, 4);
+
+    progress.println("before".into());
+    assert_eq!(output.take(), "before\n");
+
+    tick(&clock, 1000);
+    observe(&mut progress, "a");
+    assert!(output.take().starts_with("\r[=====               ] 1/4"));
+
+    tick(&clock, 50);
+    progress.println("one".into());
+    progress.eprintln("two".into());
+    observe(&mut progress, "b");
+    assert_eq!(output.take(), "");
+
+    tick(&clock, 50);
+    progress.println("three".into());
+
+    let drawn = output.take();
+    assert!(
+      drawn
+        .starts_with("\r\x1b[Kone\ntwo\nthree\n\r[==========          ] 2/4"),
+      "{drawn:?}"
+    );
+    assert!(drawn.ends_with(" b\x1b[K"), "{drawn:?}");
+
+    progress.finish();
+    assert!(output.take().starts_with("\r[====================] 4/4"));
+  }
+
+  #[test]
+  fn bar_throttles_redraws() {
+    let (mut progress, clock, output) = clocked(ProgressStyle::Bar, 10);
+
+    tick(&clock, 1000);
+    observe(&mut progress, "a");
+    assert_eq!(output.take().matches('\r').count(), 1);
+
+    for _ in 0..4 {
+      tick(&clock, 20);
+      observe(&mut progress, "b");
+    }
+    assert_eq!(output.take(), "");
+
+    tick(&clock, 20);
+    observe(&mut progress, "c");
+    assert!(output.take().contains(" 6/10 "));
+  }
+
+  #[test]
+  fn bar_truncates_path() {
+    assert_eq!(
+      progress(200, 200, "abcdefghij").bar(Duration::from_secs(1), 44),
+      "[====================] 200/200 200/s ETA 0:00"
+    );
+
+    assert_eq!(
+      progress(200, 200, "abcdefghij").bar(Duration::from_secs(1), 50),
+      "[====================] 200/200 200/s ETA 0:00 …hij"
+    );
+  }
+
+  #[test]
+  fn clock() {
+    assert_eq!(Progress::clock(Duration::from_secs(62)), "1:02");
+    assert_eq!(Progress::clock(Duration::from_secs(3723)), "1:02:03");
+  }
+
+  #[test]
+  fn line() {
+    assert_eq!(
+      progress(1234, 200_000, "a b/c.txt").line(Duration::from_secs(10)),
+      "progress done=1234 total=200000 rate=123.4 eta=1610 path=a b/c.txt"
+    );
+  }
+
+  #[test]
+  fn lines_wait_for_delay() {
+    let (mut progress, clock, output) = clocked(ProgressStyle::Lines, 4);
+
+    tick(&clock, 999);
+    observe(&mut progress, "a");
+    progress.println("a -> b".into());
+    assert_eq!(output.take(), "a -> b\n");
+
+    progress.finish();
+    assert_eq!(output.take(), "");
+
+    tick(&clock, 1);
+    observe(&mut progress, "b");
+    assert_eq!(
+      output.take(),
+      "progress done=2 total=4 rate=2.0 eta=1 path=b\n"
+    );
+
+    tick(&clock, 500);
+    observe(&mut progress, "c");
+    progress.println("c -> d".into());
+    assert_eq!(output.take(), "c -> d\n");
+
+    tick(&clock, 500);
+    observe(&mut progress, "d");
+    progress.finish();
+    assert_eq!(
+      output.take(),
+      "progress done=4 total=4 rate=2.0 eta=0 path=d\n\
+       progress done=4 total=4 rate=2.0 eta=0 path=d\n"
+    );
+  }
+}
@@ -0,0 +1,12 @@
+use super::*;
+
+/// How to report progress while renaming. `Auto` draws a bar when stderr is a
+/// terminal, and prints lines otherwise.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub(crate) enum ProgressStyle {
+  #[default]
+  Auto,
+  Bar,
+  Lines,
+  Never,
+}
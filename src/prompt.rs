@@ -0,0 +1,10 @@
+use super::*;
+
+/// What the terminal UI is waiting on, other than moving between rows.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Prompt {
+  Confirm,
+  Edit(Input),
+  Find(Input),
+  Replace { input: Input, pattern: String },
+}
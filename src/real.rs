@@ -0,0 +1,59 @@
+use super::*;
+
+/// The filesystem on disk.
+///
+/// Temporary paths handed out by [`Filesystem::temporary`] are removed when
+/// this value is dropped, unless [`Real::keep`] is called.
+#[derive(Debug, Default)]
+pub struct Real {
+  intermediates: Vec<Intermediate>,
+}
+
+impl Real {
+  /// Leaves the temporary paths handed out so far in place when this value is
+  /// dropped, so that content parked in them by a run which then fails can
+  /// still be moved on by `edmv resume`.
+  pub(crate) fn keep(&mut self) -> io::Result<()> {
+    self
+      .intermediates
+      .drain(..)
+      .try_for_each(Intermediate::keep)
+  }
+}
+
+impl Filesystem for Real {
+  fn create(&mut self, path: &Path, kind: Kind) -> io::Result<()> {
+    match kind {
+      Kind::Directory => fs::create_dir(path),
+      Kind::File => fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map(|_| ()),
+    }
+  }
+
+  fn metadata(&self, path: &Path) -> Option<Metadata> {
+    fs::metadata(path).ok().map(Metadata::from)
+  }
+
+  fn read_dir(&self, path: &Path) -> io::Result<Vec<OsString>> {
+    fs::read_dir(path)?
+      .map(|entry| entry.map(|entry| entry.file_name()))
+      .collect()
+  }
+
+  fn rename(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+    fs::rename(from, to)
+  }
+
+  fn temporary(&mut self, kind: Kind) -> io::Result<PathBuf> {
+    let intermediate = Intermediate::new(kind)?;
+
+    let path = intermediate.path().to_path_buf();
+
+    self.intermediates.push(intermediate);
+
+    Ok(path)
+  }
+}
@@ -0,0 +1,10 @@
+use super::*;
+
+/// A path within a [`Document`], along with what it is rewritten to.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Reference {
+  pub(crate) line: usize,
+  pub(crate) original: String,
+  pub(crate) range: Range<usize>,
+  pub(crate) replacement: String,
+}
@@ -0,0 +1,52 @@
+use super::*;
+
+/// Which part of each path is shown in the buffer and may be edited.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum Scope {
+  Basename,
+  #[default]
+  Path,
+  Stem,
+}
+
+impl Scope {
+  /// Returns `source` with its editable part replaced by `name`, keeping any
+  /// trailing separator of `source`.
+  pub(crate) fn join(self, source: &str, name: &str) -> String {
+    let (parent, _, extension) = Self::split(source);
+
+    let trailing = &source[source.trim_end_matches(is_separator).len()..];
+
+    match self {
+      Self::Basename => format!("{parent}{name}{trailing}"),
+      Self::Path => name.to_owned(),
+      Self::Stem => format!("{parent}{name}{extension}{trailing}"),
+    }
+  }
+
+  /// Returns the editable part of `source`.
+  pub(crate) fn name(self, source: &str) -> String {
+    let (_, stem, extension) = Self::split(source);
+
+    match self {
+      Self::Basename => format!("{stem}{extension}"),
+      Self::Path => source.to_owned(),
+      Self::Stem => stem.to_owned(),
+    }
+  }
+
+  /// Splits `path` into its parent, including the trailing separator, and
+  /// the stem and extension, including the leading `.`, of its final
+  /// component.
+  fn split(path: &str) -> (&str, &str, &str) {
+    let trimmed = path.trim_end_matches(is_separator);
+
+    let (parent, name) = trimmed
+      .split_at(trimmed.rfind(is_separator).map_or(0, |index| index + 1));
+
+    match name.rfind('.').filter(|&index| index > 0 && name != "..") {
+      Some(index) => (parent, &name[..index], &name[index..]),
+      None => (parent, name, ""),
+    }
+  }
+}
@@ -0,0 +1,24 @@
+use super::*;
+
+/// Holds the terminal in raw mode on the alternate screen, restoring it when
+/// dropped.
+pub(crate) struct Screen;
+
+impl Drop for Screen {
+  fn drop(&mut self) {
+    execute!(io::stdout(), Show, LeaveAlternateScreen).ok();
+    terminal::disable_raw_mode().ok();
+  }
+}
+
+impl Screen {
+  pub(crate) fn enter() -> io::Result<Self> {
+    terminal::enable_raw_mode()?;
+
+    let screen = Self;
+
+    execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+
+    Ok(screen)
+  }
+}
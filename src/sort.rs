@@ -0,0 +1,103 @@
+use super::*;
+
+/// The order in which sources are listed in the buffer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub(crate) enum Sort {
+  Ext,
+  Mtime,
+  Name,
+  Natural,
+  #[default]
+  None,
+  Size,
+}
+
+impl Sort {
+  fn extension(path: &str) -> String {
+    Path::new(path)
+      .extension()
+      .map(|extension| extension.to_string_lossy().to_lowercase())
+      .unwrap_or_default()
+  }
+
+  /// Compares `a` and `b` as a person would, treating runs of digits as
+  /// numbers and ignoring case, so that `file2` comes before `File10`. Only
+  /// ASCII digits count as numbers, and other characters are compared by
+  /// code point once lowercased, without regard to locale.
+  pub(crate) fn natural(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+
+    loop {
+      match (a.peek().copied(), b.peek().copied()) {
+        (None, None) => return Ordering::Equal,
+        (None, Some(_)) => return Ordering::Less,
+        (Some(_), None) => return Ordering::Greater,
+        (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+          let (x, y) = (Self::number(&mut a), Self::number(&mut b));
+
+          let ordering = x
+            .trim_start_matches('0')
+            .len()
+            .cmp(&y.trim_start_matches('0').len())
+            .then_with(|| {
+              x.trim_start_matches('0').cmp(y.trim_start_matches('0'))
+            })
+            .then_with(|| x.len().cmp(&y.len()));
+
+          if ordering != Ordering::Equal {
+            return ordering;
+          }
+        }
+        (Some(x), Some(y)) => {
+          let ordering = x.to_lowercase().cmp(y.to_lowercase());
+
+          if ordering != Ordering::Equal {
+            return ordering;
+          }
+
+          a.next();
+          b.next();
+        }
+      }
+    }
+  }
+
+  fn number(chars: &mut iter::Peekable<std::str::Chars>) -> String {
+    let mut number = String::new();
+
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+      number.push(c);
+    }
+
+    number
+  }
+
+  /// Reorders `sources`, breaking ties in natural order, and reverses the
+  /// result with `reverse`.
+  pub(crate) fn sort(self, sources: &mut [String], reverse: bool) {
+    match self {
+      Self::Name => sources.sort(),
+      Self::None => {}
+      Self::Ext | Self::Mtime | Self::Natural | Self::Size => {
+        sources.sort_by(|a, b| Self::natural(a, b));
+      }
+    }
+
+    let metadata = |path: &str| fs::symlink_metadata(path).ok();
+
+    match self {
+      Self::Ext => sources.sort_by_cached_key(|path| Self::extension(path)),
+      Self::Mtime => sources.sort_by_cached_key(|path| {
+        metadata(path).and_then(|metadata| metadata.modified().ok())
+      }),
+      Self::Size => sources.sort_by_cached_key(|path| {
+        metadata(path).map(|metadata| metadata.len())
+      }),
+      Self::Name | Self::Natural | Self::None => {}
+    }
+
+    if reverse {
+      sources.reverse();
+    }
+  }
+}
@@ -0,0 +1,221 @@
+use super::*;
+
+/// A single step of a run, as recorded in a [`Journal`], with every path
+/// made absolute.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Step {
+  Create {
+    kind: Kind,
+    path: PathBuf,
+  },
+  Document {
+    content: String,
+    destination: PathBuf,
+    path: PathBuf,
+    rewritten: String,
+  },
+  Link {
+    destination: PathBuf,
+    path: PathBuf,
+    replacement: PathBuf,
+    target: PathBuf,
+  },
+  Rename {
+    from: PathBuf,
+    to: PathBuf,
+  },
+}
+
+impl Display for Step {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      Self::Create { path, .. } => write!(f, "created {}", path.display()),
+      Self::Document { path, .. } => write!(f, "updated {}", path.display()),
+      Self::Link { path, .. } => write!(f, "retargeted {}", path.display()),
+      Self::Rename { from, to } => {
+        write!(f, "{} -> {}", from.display(), to.display())
+      }
+    }
+  }
+}
+
+impl Step {
+  /// Whether the filesystem shows this step as already made.
+  pub(crate) fn done(&self) -> bool {
+    match self {
+      Self::Create { path, .. } => fs::symlink_metadata(path).is_ok(),
+      Self::Document {
+        destination,
+        rewritten,
+        ..
+      } => fs::read_to_string(destination)
+        .is_ok_and(|content| content == *rewritten),
+      Self::Link {
+        destination,
+        replacement,
+        ..
+      } => {
+        fs::read_link(destination).is_ok_and(|target| target == *replacement)
+      }
+      Self::Rename { from, to } => {
+        fs::symlink_metadata(from).is_err() && fs::symlink_metadata(to).is_ok()
+      }
+    }
+  }
+
+  /// Reads a step back from a table written by [`Step::table`].
+  pub(crate) fn parse(table: &Table) -> Option<Self> {
+    let string = |key: &str| table.get(key)?.as_str().map(str::to_owned);
+
+    let path = |key: &str| string(key).map(PathBuf::from);
+
+    Some(match table.get("type")?.as_str()? {
+      "create" => Self::Create {
+        kind: match string("kind")?.as_str() {
+          "directory" => Kind::Directory,
+          "file" => Kind::File,
+          _ => return None,
+        },
+        path: path("path")?,
+      },
+      "document" => Self::Document {
+        content: string("content")?,
+        destination: path("destination")?,
+        path: path("path")?,
+        rewritten: string("rewritten")?,
+      },
+      "link" => Self::Link {
+        destination: path("destination")?,
+        path: path("path")?,
+        replacement: path("replacement")?,
+        target: path("target")?,
+      },
+      "rename" => Self::Rename {
+        from: path("from")?,
+        to: path("to")?,
+      },
+      _ => return None,
+    })
+  }
+
+  /// The paths this step changes.
+  pub(crate) fn paths(&self) -> Vec<&Path> {
+    match self {
+      Self::Create { path, .. } => vec![path],
+      Self::Document {
+        destination, path, ..
+      }
+      | Self::Link {
+        destination, path, ..
+      } => vec![path, destination],
+      Self::Rename { from, to } => vec![from, to],
+    }
+  }
+
+  /// Makes the step, refusing to overwrite a document or link that no longer
+  /// holds what it did when the step was recorded.
+  pub(crate) fn perform(&self, filesystem: &mut dyn Filesystem) -> Result {
+    match self {
+      Self::Create { kind, path } => {
+        filesystem
+          .create(path, *kind)
+          .map_err(|source| Error::Create {
+            path: path.clone(),
+            source,
+          })
+      }
+      Self::Document {
+        content,
+        destination,
+        path,
+        rewritten,
+      } => {
+        if fs::read_to_string(destination)? != *content {
+          return Err(Error::ChangedDocument { path: path.clone() });
+        }
+
+        fs::write(destination, rewritten)?;
+
+        Ok(())
+      }
+      Self::Link {
+        destination,
+        path,
+        replacement,
+        target,
+      } => Link::new(
+        destination.clone(),
+        path.clone(),
+        replacement.clone(),
+        target.clone(),
+      )
+      .apply(),
+      Self::Rename { from, to } => {
+        filesystem.rename(from, to).map_err(|source| Error::Rename {
+          from: from.clone(),
+          source,
+          to: to.clone(),
+        })
+      }
+    }
+  }
+
+  /// Writes the step as a table, or returns `None` if one of its paths isn't
+  /// valid UTF-8.
+  pub(crate) fn table(&self) -> Option<Table> {
+    let (kind, fields) = match self {
+      Self::Create { kind, path } => (
+        "create",
+        vec![
+          (
+            "kind",
+            match kind {
+              Kind::Directory => "directory",
+              Kind::File => "file",
+            },
+          ),
+          ("path", path.to_str()?),
+        ],
+      ),
+      Self::Document {
+        content,
+        destination,
+        path,
+        rewritten,
+      } => (
+        "document",
+        vec![
+          ("content", content.as_str()),
+          ("destination", destination.to_str()?),
+          ("path", path.to_str()?),
+          ("rewritten", rewritten.as_str()),
+        ],
+      ),
+      Self::Link {
+        destination,
+        path,
+        replacement,
+        target,
+      } => (
+        "link",
+        vec![
+          ("destination", destination.to_str()?),
+          ("path", path.to_str()?),
+          ("replacement", replacement.to_str()?),
+          ("target", target.to_str()?),
+        ],
+      ),
+      Self::Rename { from, to } => (
+        "rename",
+        vec![("from", from.to_str()?), ("to", to.to_str()?)],
+      ),
+    };
+
+    Some(
+      iter::once(("type", kind))
+        .chain(fields)
+        .map(|(key, value)| (key.to_owned(), Value::String(value.to_owned())))
+        .collect(),
+    )
+  }
+}
@@ -0,0 +1,48 @@
+use super::*;
+
+/// Commands run instead of editing a batch of renames: printing files for
+/// packagers, generated from the definition of [`Arguments`], or finishing an
+/// interrupted run.
+#[derive(Debug, clap::Subcommand)]
+pub(crate) enum Subcommand {
+  #[command(about = "Print a completion script for SHELL")]
+  Completions {
+    #[arg(value_enum, help = "Shell to print completions for")]
+    shell: Shell,
+  },
+  #[command(about = "Print a roff man page")]
+  Man,
+  #[command(about = "Finish runs that were interrupted, from their journals")]
+  Resume,
+}
+
+impl Subcommand {
+  fn print(output: &[u8]) -> Result {
+    io::stdout().write_all(output)?;
+
+    Ok(())
+  }
+
+  pub(crate) fn run(self) -> Result {
+    let mut output = Vec::new();
+
+    match self {
+      Self::Completions { shell } => {
+        clap_complete::generate(
+          shell,
+          &mut Arguments::command(),
+          env!("CARGO_PKG_NAME"),
+          &mut output,
+        );
+
+        Self::print(&output)
+      }
+      Self::Man => {
+        clap_mangen::Man::new(Arguments::command()).render(&mut output)?;
+
+        Self::print(&output)
+      }
+      Self::Resume => Journal::resume(),
+    }
+  }
+}
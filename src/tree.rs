@@ -0,0 +1,476 @@
+use super::*;
+
+const ESCAPE: char = '\\';
+const INDENT: &str = "  ";
+
+/// A path shown on its own line of a [`Tree`].
+#[derive(Debug)]
+struct Branch {
+  depth: usize,
+  kind: Kind,
+  name: String,
+  path: PathBuf,
+}
+
+/// A line of an edited tree, placed beneath the closest preceding line that
+/// is indented less than it.
+#[derive(Debug)]
+struct Line {
+  directory: bool,
+  id: Option<usize>,
+  name: String,
+  parent: Option<usize>,
+}
+
+/// The directory hierarchy below a set of sources, rendered one path per
+/// line with indentation, each line prefixed by an ID.
+#[derive(Debug)]
+pub(crate) struct Tree {
+  branches: Vec<Branch>,
+}
+
+impl Display for Tree {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    let width = self.branches.len().to_string().len();
+
+    for (index, branch) in self.branches.iter().enumerate() {
+      writeln!(
+        f,
+        "{:0width$} {}{}{}",
+        index + 1,
+        INDENT.repeat(branch.depth),
+        Self::escape(&branch.name),
+        if branch.kind == Kind::Directory {
+          "/"
+        } else {
+          ""
+        }
+      )?;
+    }
+
+    Ok(())
+  }
+}
+
+impl Tree {
+  fn add(
+    branches: &mut Vec<Branch>,
+    branch: Branch,
+    filter: &Filter,
+    ignores: &mut Vec<Ignore>,
+  ) -> io::Result<()> {
+    let (path, depth) = (branch.path.clone(), branch.depth);
+
+    let directory = branch.kind == Kind::Directory;
+
+    branches.push(branch);
+
+    if directory {
+      ignores.push(Ignore::load(&path.absolutize()?));
+
+      let mut names = fs::read_dir(&path)?
+        .map(|entry| entry.map(|entry| entry.file_name()))
+        .collect::<io::Result<Vec<OsString>>>()?;
+
+      names.sort();
+
+      for name in names {
+        if name == Lock::NAME {
+          continue;
+        }
+
+        let path = path.join(&name);
+
+        let kind = Self::kind(&path)?;
+
+        let absolute = path.absolutize()?;
+
+        if filter.excludes(&path)
+          || (kind == Kind::File && !filter.includes(&path, kind))
+          || ignores
+            .iter()
+            .rev()
+            .find_map(|ignore| ignore.ignores(&absolute, kind))
+            .unwrap_or_default()
+        {
+          continue;
+        }
+
+        Self::add(
+          branches,
+          Branch {
+            depth: depth + 1,
+            kind,
+            name: name.to_string_lossy().into_owned(),
+            path,
+          },
+          filter,
+          ignores,
+        )?;
+      }
+
+      ignores.pop();
+    }
+
+    Ok(())
+  }
+
+  fn escape(name: &str) -> String {
+    if name.starts_with([' ', '#', ESCAPE]) {
+      format!("{ESCAPE}{name}")
+    } else {
+      name.to_owned()
+    }
+  }
+
+  fn kind(path: &Path) -> io::Result<Kind> {
+    Ok(if fs::symlink_metadata(path)?.is_dir() {
+      Kind::Directory
+    } else {
+      Kind::File
+    })
+  }
+
+  /// Where the path on `line` ends up, in terms of where each directory
+  /// containing it was before renaming.
+  fn location(&self, lines: &[Line], line: usize) -> PathBuf {
+    let line = &lines[line];
+
+    let branch = line
+      .id
+      .map(|id| &self.branches[id])
+      .filter(|branch| branch.name == line.name);
+
+    let Some(parent) = line.parent else {
+      return match branch {
+        Some(branch) if branch.depth == 0 => branch.path.clone(),
+        _ => PathBuf::from(&line.name),
+      };
+    };
+
+    let name = branch
+      .and_then(|branch| branch.path.file_name())
+      .map_or_else(|| PathBuf::from(&line.name), PathBuf::from);
+
+    match lines[parent].id {
+      Some(id) => self.branches[id].path.join(name),
+      None => self.location(lines, parent).join(name),
+    }
+  }
+
+  /// Lists the hierarchy below each of `sources`, skipping any source that
+  /// lies beneath another one or repeats an earlier one. Within it, paths
+  /// rejected by `filter` or by ignore files in the current directory or below
+  /// are left out, though directories are kept whatever their type or
+  /// extension.
+  pub(crate) fn new(sources: &[String], filter: &Filter) -> Result<Self> {
+    let mut branches = Vec::new();
+
+    let current = env::current_dir()?;
+
+    let roots = sources
+      .iter()
+      .map(|source| Ok(Path::new(source).absolutize()?.into_owned()))
+      .collect::<Result<Vec<PathBuf>>>()?;
+
+    for (index, (source, absolute)) in sources.iter().zip(&roots).enumerate() {
+      if roots.iter().enumerate().any(|(other, root)| {
+        absolute.starts_with(root) && (absolute != root || other < index)
+      }) {
+        continue;
+      }
+
+      let mut ignores = absolute
+        .ancestors()
+        .skip(1)
+        .take_while(|ancestor| ancestor.starts_with(&current))
+        .map(Ignore::load)
+        .collect::<Vec<Ignore>>();
+
+      ignores.reverse();
+
+      let name = source.trim_end_matches(is_separator);
+
+      Self::add(
+        &mut branches,
+        Branch {
+          depth: 0,
+          kind: Self::kind(Path::new(source))?,
+          name: if name.is_empty() { source } else { name }.to_owned(),
+          path: PathBuf::from(source),
+        },
+        filter,
+        &mut ignores,
+      )?;
+    }
+
+    Ok(Self { branches })
+  }
+
+  /// Orders `pairs` so that each path is renamed before any directory
+  /// containing its source or destination is, returning the sources of the
+  /// renames that have to happen before such a directory is renamed.
+  fn order(pairs: &mut Vec<(PathBuf, PathBuf)>) -> Result<Vec<String>> {
+    let sources = pairs
+      .iter()
+      .enumerate()
+      .map(|(index, (source, _))| (source.as_path(), index))
+      .collect::<HashMap<&Path, usize>>();
+
+    let mut later = vec![BTreeSet::new(); pairs.len()];
+
+    let mut waiting = vec![0; pairs.len()];
+
+    for (index, (source, destination)) in pairs.iter().enumerate() {
+      for ancestor in source
+        .ancestors()
+        .skip(1)
+        .chain(destination.ancestors().skip(1))
+      {
+        if let Some(&other) = sources.get(ancestor) {
+          if other != index && later[index].insert(other) {
+            waiting[other] += 1;
+          }
+        }
+      }
+    }
+
+    let inside = pairs
+      .iter()
+      .filter(|(source, destination)| destination.starts_with(source))
+      .map(|(source, _)| source.display().to_string())
+      .collect::<Vec<String>>();
+
+    if !inside.is_empty() {
+      return Err(Error::CircularRenames { sources: inside });
+    }
+
+    let nested = (0..pairs.len())
+      .filter(|&index| !later[index].is_empty())
+      .map(|index| pairs[index].0.display().to_string())
+      .collect();
+
+    let mut ready = (0..pairs.len())
+      .filter(|&index| waiting[index] == 0)
+      .collect::<BTreeSet<usize>>();
+
+    let mut order = Vec::new();
+
+    while let Some(index) = ready.pop_first() {
+      order.push(index);
+
+      for &other in &later[index] {
+        waiting[other] -= 1;
+
+        if waiting[other] == 0 {
+          ready.insert(other);
+        }
+      }
+    }
+
+    if order.len() < pairs.len() {
+      let circular = (0..pairs.len())
+        .filter(|index| !order.contains(index))
+        .map(|index| pairs[index].0.display().to_string())
+        .collect();
+
+      return Err(Error::CircularRenames { sources: circular });
+    }
+
+    let mut slots = mem::take(pairs).into_iter().map(Some).collect::<Vec<_>>();
+
+    pairs.extend(order.into_iter().filter_map(|index| slots[index].take()));
+
+    Ok(nested)
+  }
+
+  /// Turns the edited tree in `content` back into the renames it describes,
+  /// ordered so they can be made one after another, and the new paths added
+  /// on lines without an ID. A directory renamed with `resolve` can't contain
+  /// any other rename, since everything is moved to a temporary path before
+  /// anything reaches its destination.
+  pub(crate) fn parse(
+    &self,
+    content: &str,
+    resolve: bool,
+  ) -> Result<(Vec<(PathBuf, PathBuf)>, Vec<(PathBuf, Kind)>)> {
+    if !self.branches.is_empty()
+      && content
+        .lines()
+        .all(|line| line.starts_with('#') || line.trim().is_empty())
+    {
+      return Err(Error::Aborted);
+    }
+
+    let mut lines = Vec::<Line>::new();
+
+    let mut stack = Vec::<(usize, usize)>::new();
+
+    let (mut unnamed, mut unknown, mut misplaced) =
+      (Vec::new(), Vec::new(), Vec::new());
+
+    for line in content.lines() {
+      if line.starts_with('#') || line.trim().is_empty() {
+        continue;
+      }
+
+      let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+
+      let (id, start) = match line[digits..].strip_prefix(' ') {
+        Some(_) if digits > 0 => {
+          let id = line[..digits]
+            .parse::<usize>()
+            .ok()
+            .and_then(|id| id.checked_sub(1))
+            .filter(|&id| id < self.branches.len());
+
+          if id.is_none() {
+            unknown.push(line[..digits].to_owned());
+            continue;
+          }
+
+          (id, digits + 1)
+        }
+        _ => (None, 0),
+      };
+
+      let indent = line.len() - line[start..].trim_start_matches(' ').len();
+
+      let body = &line[indent..];
+
+      let (body, slash) = match body.strip_suffix('/') {
+        Some(body) => (body, true),
+        None => (body, false),
+      };
+
+      let name = body.strip_prefix(ESCAPE).unwrap_or(body).to_owned();
+
+      if id.is_none() && name.is_empty() {
+        unnamed.push(line.trim().to_owned());
+        continue;
+      }
+
+      while stack.last().is_some_and(|&(depth, _)| depth >= indent) {
+        stack.pop();
+      }
+
+      let parent = stack.last().map(|&(_, parent)| parent);
+
+      if parent.is_some_and(|parent| !lines[parent].directory) {
+        misplaced.push(name.clone());
+      }
+
+      stack.push((indent, lines.len()));
+
+      lines.push(Line {
+        directory: id
+          .map_or(slash, |id| self.branches[id].kind == Kind::Directory),
+        id,
+        name,
+        parent,
+      });
+    }
+
+    if !unknown.is_empty() {
+      return Err(Error::UnknownIds { ids: unknown });
+    }
+
+    if !unnamed.is_empty() {
+      return Err(Error::UnnamedLines { lines: unnamed });
+    }
+
+    let mut placed = vec![None; self.branches.len()];
+
+    let mut repeated = Vec::new();
+
+    for (index, line) in lines.iter().enumerate() {
+      if let Some(id) = line.id {
+        let source = self.branches[id].path.display().to_string();
+
+        if placed[id].replace(index).is_some() && !repeated.contains(&source) {
+          repeated.push(source);
+        }
+      }
+    }
+
+    if !repeated.is_empty() {
+      return Err(Error::RepeatedSources { sources: repeated });
+    }
+
+    let missing = placed
+      .iter()
+      .zip(&self.branches)
+      .filter(|(placed, _)| placed.is_none())
+      .map(|(_, branch)| branch.path.display().to_string())
+      .collect::<Vec<String>>();
+
+    if !missing.is_empty() {
+      return Err(Error::MissingSources { sources: missing });
+    }
+
+    if !misplaced.is_empty() {
+      return Err(Error::FileParents { names: misplaced });
+    }
+
+    let empty = lines
+      .iter()
+      .filter(|line| line.name.is_empty())
+      .filter_map(|line| line.id)
+      .map(|id| self.branches[id].path.display().to_string())
+      .collect::<Vec<String>>();
+
+    if !empty.is_empty() {
+      return Err(Error::EmptyDestinations { sources: empty });
+    }
+
+    let separated = lines
+      .iter()
+      .filter(|line| {
+        line.parent.is_some()
+          && line.name.contains(is_separator)
+          && line.id.is_none_or(|id| self.branches[id].name != line.name)
+      })
+      .map(|line| line.name.clone())
+      .collect::<Vec<String>>();
+
+    if !separated.is_empty() {
+      return Err(Error::SeparatedNames { names: separated });
+    }
+
+    let mut pairs = lines
+      .iter()
+      .enumerate()
+      .filter_map(|(index, line)| {
+        let id = line.id?;
+
+        let destination = self.location(&lines, index);
+
+        (destination != self.branches[id].path)
+          .then(|| (self.branches[id].path.clone(), destination))
+      })
+      .collect::<Vec<(PathBuf, PathBuf)>>();
+
+    let nested = Self::order(&mut pairs)?;
+
+    if resolve && !nested.is_empty() {
+      return Err(Error::NestedRenames { sources: nested });
+    }
+
+    let creations = lines
+      .iter()
+      .enumerate()
+      .filter(|(_, line)| line.id.is_none())
+      .map(|(index, line)| {
+        let kind = if line.directory {
+          Kind::Directory
+        } else {
+          Kind::File
+        };
+
+        (self.location(&lines, index), kind)
+      })
+      .collect();
+
+    Ok((pairs, creations))
+  }
+}
@@ -0,0 +1,568 @@
+use super::*;
+
+/// A full-screen list of sources and their destinations, edited in place of
+/// the buffer with `--tui`.
+pub(crate) struct Tui<'a> {
+  cursor: usize,
+  destinations: Vec<String>,
+  filesystem: &'a dyn Filesystem,
+  force: bool,
+  markers: Vec<Vec<String>>,
+  offset: usize,
+  page: usize,
+  prompt: Option<Prompt>,
+  resolve: bool,
+  sources: &'a [String],
+  status: Option<String>,
+}
+
+impl<'a> Tui<'a> {
+  fn browse(&mut self, key: KeyEvent) -> Result {
+    let last = self.sources.len().saturating_sub(1);
+
+    match key.code {
+      KeyCode::Char('/') => self.prompt = Some(Prompt::Find(Input::default())),
+      KeyCode::Char('e' | 'i') | KeyCode::Enter if !self.sources.is_empty() => {
+        self.prompt = Some(Prompt::Edit(Input::new(
+          self.destinations[self.cursor].clone(),
+        )));
+      }
+      KeyCode::Char('G') | KeyCode::End => self.cursor = last,
+      KeyCode::Char('g') | KeyCode::Home => self.cursor = 0,
+      KeyCode::Char('j') | KeyCode::Down => {
+        self.cursor = (self.cursor + 1).min(last);
+      }
+      KeyCode::Char('k') | KeyCode::Up => {
+        self.cursor = self.cursor.saturating_sub(1);
+      }
+      KeyCode::Char('q') | KeyCode::Esc => self.prompt = Some(Prompt::Confirm),
+      KeyCode::Char('u') if !self.sources.is_empty() => {
+        self.destinations[self.cursor] = self.sources[self.cursor].clone();
+        self.refresh()?;
+      }
+      KeyCode::PageDown => self.cursor = (self.cursor + self.page).min(last),
+      KeyCode::PageUp => self.cursor = self.cursor.saturating_sub(self.page),
+      _ => {}
+    }
+
+    Ok(())
+  }
+
+  fn changed(&self) -> usize {
+    self
+      .sources
+      .iter()
+      .zip(&self.destinations)
+      .filter(|(source, destination)| source != destination)
+      .count()
+  }
+
+  fn current(&self) -> Vec<String> {
+    let mut destinations = self.destinations.clone();
+
+    if let Some(Prompt::Edit(input)) = &self.prompt {
+      input.text().clone_into(&mut destinations[self.cursor]);
+    }
+
+    destinations
+  }
+
+  fn draw(&mut self, out: &mut impl Write) -> io::Result<()> {
+    let (width, height) = terminal::size()?;
+
+    let (width, height) = (usize::from(width), usize::from(height));
+
+    let row = |index: usize| u16::try_from(index).unwrap_or(u16::MAX);
+
+    self.page = height.saturating_sub(2).max(1);
+
+    if self.cursor < self.offset {
+      self.offset = self.cursor;
+    } else if self.cursor >= self.offset + self.page {
+      self.offset = self.cursor + 1 - self.page;
+    }
+
+    queue!(
+      out,
+      Hide,
+      Clear(ClearType::All),
+      MoveTo(0, 0),
+      SetAttribute(Attribute::Reverse),
+      Print(Self::fit(
+        &format!(
+          " {}: {} path(s), {} changed, {} marked",
+          env!("CARGO_PKG_NAME"),
+          self.sources.len(),
+          self.changed(),
+          self.flagged()
+        ),
+        width
+      )),
+      SetAttribute(Attribute::Reset),
+    )?;
+
+    let column = self
+      .sources
+      .iter()
+      .map(|source| source.chars().count())
+      .max()
+      .unwrap_or(0)
+      .min(width.saturating_sub(8) / 2);
+
+    let mut position = None;
+
+    for (line, index) in (self.offset..self.sources.len())
+      .take(self.page)
+      .enumerate()
+    {
+      let selected = index == self.cursor;
+
+      let editing = match &self.prompt {
+        Some(Prompt::Edit(input)) if selected => Some(input),
+        _ => None,
+      };
+
+      let destination =
+        editing.map_or(self.destinations[index].as_str(), Input::text);
+
+      let prefix = format!(
+        "{} {:column$} -> ",
+        if selected { '>' } else { ' ' },
+        Self::fit(&self.sources[index], column)
+      );
+
+      let prefix = Self::fit(&prefix, width);
+
+      let remaining = width.saturating_sub(prefix.chars().count());
+
+      if let Some(input) = editing {
+        position = Some((
+          row((prefix.chars().count() + input.cursor()).min(width)),
+          row(line + 1),
+        ));
+      }
+
+      queue!(out, MoveTo(0, row(line + 1)))?;
+
+      if selected {
+        queue!(out, SetAttribute(Attribute::Bold))?;
+      }
+
+      queue!(out, Print(&prefix))?;
+
+      if editing.is_none() && destination == self.sources[index] {
+        queue!(out, SetAttribute(Attribute::Dim))?;
+      }
+
+      queue!(
+        out,
+        Print(Self::fit(destination, remaining)),
+        SetAttribute(Attribute::Reset)
+      )?;
+
+      let remaining =
+        remaining.saturating_sub(destination.chars().count().min(remaining));
+
+      if !self.markers[index].is_empty() {
+        queue!(
+          out,
+          SetForegroundColor(Color::Red),
+          Print(Self::fit(
+            &format!("  [{}]", self.markers[index].join(", ")),
+            remaining
+          )),
+          ResetColor
+        )?;
+      }
+    }
+
+    let (footer, input) = match &self.prompt {
+      Some(Prompt::Confirm) => match self.flagged() {
+        0 => (
+          format!(
+            "Apply {} rename(s)? [y]es, [n]o to cancel, [esc] to keep editing",
+            self.changed()
+          ),
+          None,
+        ),
+        flagged => (
+          format!(
+            "{flagged} path(s) are marked, [n]o to cancel, [esc] to keep editing"
+          ),
+          None,
+        ),
+      },
+      Some(Prompt::Find(input)) => (format!("Find: {}", input.text()), Some(input)),
+      Some(Prompt::Replace { input, pattern }) => (
+        format!("Replace `{pattern}` with: {}", input.text()),
+        Some(input),
+      ),
+      Some(Prompt::Edit(_)) => (
+        "[enter] to keep, [esc] to discard".to_owned(),
+        None,
+      ),
+      None => (
+        self.status.clone().unwrap_or_else(|| {
+          "[enter] edit, [/] find and replace, [u] undo, [q] apply or cancel"
+            .to_owned()
+        }),
+        None,
+      ),
+    };
+
+    let bottom = row(height.saturating_sub(1));
+
+    queue!(out, MoveTo(0, bottom), Print(Self::fit(&footer, width)))?;
+
+    if let Some(input) = input {
+      position = Some((
+        row(
+          (footer.chars().count() - input.text().chars().count()
+            + input.cursor())
+          .min(width),
+        ),
+        bottom,
+      ));
+    }
+
+    if let Some((x, y)) = position {
+      queue!(out, MoveTo(x, y), Show)?;
+    }
+
+    out.flush()
+  }
+
+  fn fit(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+  }
+
+  fn flagged(&self) -> usize {
+    self
+      .markers
+      .iter()
+      .filter(|markers| !markers.is_empty())
+      .count()
+  }
+
+  fn handle(&mut self, key: KeyEvent) -> Result<Option<Vec<String>>> {
+    if key.modifiers.contains(KeyModifiers::CONTROL)
+      && key.code == KeyCode::Char('c')
+    {
+      return Err(Error::Aborted);
+    }
+
+    self.status = None;
+
+    match self.prompt.take() {
+      None => self.browse(key)?,
+      Some(Prompt::Confirm) => match key.code {
+        KeyCode::Char('y') | KeyCode::Enter => match self.flagged() {
+          0 => return Ok(Some(self.destinations.clone())),
+          flagged => {
+            self.status =
+              Some(format!("Fix the {flagged} marked path(s) before applying"));
+          }
+        },
+        KeyCode::Char('n') => return Err(Error::Aborted),
+        _ => {}
+      },
+      Some(Prompt::Edit(mut input)) => match key.code {
+        KeyCode::Enter => {
+          self.destinations[self.cursor] = input.into_text();
+        }
+        KeyCode::Esc => self.refresh()?,
+        _ => {
+          input.handle(key);
+          self.prompt = Some(Prompt::Edit(input));
+          self.refresh()?;
+        }
+      },
+      Some(Prompt::Find(mut input)) => match key.code {
+        KeyCode::Enter if !input.text().is_empty() => {
+          self.prompt = Some(Prompt::Replace {
+            input: Input::default(),
+            pattern: input.into_text(),
+          });
+        }
+        KeyCode::Enter | KeyCode::Esc => {}
+        _ => {
+          input.handle(key);
+          self.prompt = Some(Prompt::Find(input));
+        }
+      },
+      Some(Prompt::Replace { mut input, pattern }) => match key.code {
+        KeyCode::Enter => self.replace(&pattern, input.text())?,
+        KeyCode::Esc => {}
+        _ => {
+          input.handle(key);
+          self.prompt = Some(Prompt::Replace { input, pattern });
+        }
+      },
+    }
+
+    Ok(None)
+  }
+
+  pub(crate) fn new(
+    filesystem: &'a dyn Filesystem,
+    force: bool,
+    resolve: bool,
+    sources: &'a [String],
+  ) -> Result<Self> {
+    let mut tui = Self {
+      cursor: 0,
+      destinations: sources.to_vec(),
+      filesystem,
+      force,
+      markers: Vec::new(),
+      offset: 0,
+      page: 1,
+      prompt: None,
+      resolve,
+      sources,
+      status: None,
+    };
+
+    tui.refresh()?;
+
+    Ok(tui)
+  }
+
+  fn refresh(&mut self) -> Result {
+    let destinations = self.current();
+
+    let changed = (0..self.sources.len())
+      .filter(|&index| {
+        !destinations[index].is_empty()
+          && destinations[index] != self.sources[index]
+      })
+      .collect::<Vec<usize>>();
+
+    let plan = Plan::new(
+      changed
+        .iter()
+        .map(|&index| {
+          (
+            PathBuf::from(&self.sources[index]),
+            PathBuf::from(&destinations[index]),
+          )
+        })
+        .collect(),
+    );
+
+    self.markers = destinations
+      .iter()
+      .map(|destination| {
+        if destination.is_empty() {
+          vec!["empty".to_owned()]
+        } else {
+          Vec::new()
+        }
+      })
+      .collect();
+
+    for (index, problems) in changed.into_iter().zip(plan.problems(
+      self.filesystem,
+      self.force,
+      self.resolve,
+    )?) {
+      self.markers[index] = problems.iter().map(ToString::to_string).collect();
+    }
+
+    Ok(())
+  }
+
+  fn replace(&mut self, pattern: &str, replacement: &str) -> Result {
+    let mut replaced = 0;
+
+    for destination in &mut self.destinations {
+      if destination.contains(pattern) {
+        *destination = destination.replace(pattern, replacement);
+        replaced += 1;
+      }
+    }
+
+    self.status = Some(format!("Replaced `{pattern}` in {replaced} path(s)"));
+
+    self.refresh()
+  }
+
+  /// Shows the list until every destination is applied, returning them in
+  /// source order, or the user cancels. Fails unless stdin and stdout are
+  /// both a terminal.
+  pub(crate) fn run(mut self) -> Result<Vec<String>> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+      return Err(Error::NotTerminal);
+    }
+
+    let _screen = Screen::enter()?;
+
+    let mut stdout = io::stdout();
+
+    loop {
+      self.draw(&mut stdout)?;
+
+      if let TerminalEvent::Key(key) = crossterm::event::read()? {
+        if key.kind == KeyEventKind::Press {
+          if let Some(destinations) = self.handle(key)? {
+            return Ok(destinations);
+          }
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn key(code: KeyCode) -> KeyEvent {
+    KeyEvent::new(code, KeyModifiers::NONE)
+  }
+
+  fn memory() -> Memory<'static> {
+    let mut memory = Memory::new();
+
+    for name in ["a.txt", "b.txt", "c.txt"] {
+      memory.create_file(&root().join(name)).unwrap();
+    }
+
+    memory
+  }
+
+  fn root() -> PathBuf {
+    PathBuf::from("/edmv")
+  }
+
+  fn sources() -> Vec<String> {
+    ["a.txt", "b.txt", "c.txt"]
+      .iter()
+      .map(|name| root().join(name).display().to_string())
+      .collect()
+  }
+
+  fn typed(tui: &mut Tui, keys: &str) {
+    for character in keys.chars() {
+      assert_eq!(tui.handle(key(KeyCode::Char(character))).unwrap(), None);
+    }
+  }
+
+  #[test]
+  fn applies_edited_destinations() {
+    let memory = memory();
+
+    let sources = sources();
+
+    let mut tui = Tui::new(&memory, false, false, &sources).unwrap();
+
+    tui.handle(key(KeyCode::Down)).unwrap();
+    tui.handle(key(KeyCode::Enter)).unwrap();
+
+    for _ in 0.."b.txt".len() {
+      tui.handle(key(KeyCode::Backspace)).unwrap();
+    }
+
+    typed(&mut tui, "d.txt");
+
+    tui.handle(key(KeyCode::Enter)).unwrap();
+    tui.handle(key(KeyCode::Char('q'))).unwrap();
+
+    assert_eq!(
+      tui.handle(key(KeyCode::Char('y'))).unwrap(),
+      Some(vec![
+        sources[0].clone(),
+        "/edmv/d.txt".to_owned(),
+        sources[2].clone()
+      ])
+    );
+  }
+
+  #[test]
+  fn cancels_without_applying() {
+    let memory = memory();
+
+    let sources = sources();
+
+    let mut tui = Tui::new(&memory, false, false, &sources).unwrap();
+
+    tui.handle(key(KeyCode::Esc)).unwrap();
+
+    assert!(matches!(
+      tui.handle(key(KeyCode::Char('n'))),
+      Err(Error::Aborted)
+    ));
+  }
+
+  #[test]
+  fn marks_problems_while_editing() {
+    let memory = memory();
+
+    let sources = sources();
+
+    let mut tui = Tui::new(&memory, false, false, &sources).unwrap();
+
+    tui.handle(key(KeyCode::Enter)).unwrap();
+
+    tui.handle(key(KeyCode::Left)).unwrap();
+    tui.handle(key(KeyCode::Left)).unwrap();
+    tui.handle(key(KeyCode::Left)).unwrap();
+    tui.handle(key(KeyCode::Left)).unwrap();
+    tui.handle(key(KeyCode::Backspace)).unwrap();
+
+    typed(&mut tui, "b");
+
+    assert_eq!(tui.markers[0], ["exists"]);
+    assert_eq!(tui.destinations[0], sources[0]);
+
+    tui.handle(key(KeyCode::Esc)).unwrap();
+
+    assert_eq!(tui.markers[0], Vec::<String>::new());
+  }
+
+  #[test]
+  fn replaces_in_every_destination() {
+    let memory = memory();
+
+    let sources = sources();
+
+    let mut tui = Tui::new(&memory, false, false, &sources).unwrap();
+
+    typed(&mut tui, "/.txt");
+
+    tui.handle(key(KeyCode::Enter)).unwrap();
+    tui.handle(key(KeyCode::Enter)).unwrap();
+
+    assert_eq!(tui.destinations, ["/edmv/a", "/edmv/b", "/edmv/c"]);
+    assert_eq!(tui.status.as_deref(), Some("Replaced `.txt` in 3 path(s)"));
+
+    typed(&mut tui, "/b");
+
+    tui.handle(key(KeyCode::Enter)).unwrap();
+
+    typed(&mut tui, "a");
+
+    tui.handle(key(KeyCode::Enter)).unwrap();
+
+    assert_eq!(
+      tui.markers,
+      [vec!["duplicate"], vec!["duplicate"], Vec::<&str>::new()]
+    );
+
+    typed(&mut tui, "q");
+
+    assert_eq!(tui.handle(key(KeyCode::Char('y'))).unwrap(), None);
+    assert_eq!(
+      tui.status.as_deref(),
+      Some("Fix the 2 marked path(s) before applying")
+    );
+
+    typed(&mut tui, "ju");
+
+    assert_eq!(
+      tui.destinations,
+      ["/edmv/a", sources[1].as_str(), "/edmv/c"]
+    );
+    assert_eq!(tui.flagged(), 0);
+  }
+}
@@ -12,7 +12,10 @@ use {
 };
 
 #[cfg(unix)]
-use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+use std::{
+  fs::Permissions, os::unix::fs::PermissionsExt, process::Stdio, thread,
+  time::Duration,
+};
 
 #[cfg(windows)]
 use {
@@ -58,6 +61,13 @@ fn main() {
     None => return,
   };
 
+  if let Ok(buffer) = env::var("EDMV_TEST_BUFFER") {
+    if let Err(error) = fs::copy(&path, buffer) {
+      eprintln!("failed to copy editor input: {error}");
+      process::exit(1);
+    }
+  }
+
   let contents = env::var("EDMV_TEST_EDITOR_CONTENT").unwrap_or_default();
   let data = format!("{contents}\n");
 
@@ -70,7 +80,10 @@ fn main() {
 
 struct Test<'a> {
   arguments: Vec<String>,
+  edit: Option<String>,
+  editor_command: Option<String>,
   exists: Vec<&'a str>,
+  expected_buffer: Option<String>,
   expected_status: i32,
   expected_stderr: String,
   expected_stdout: String,
@@ -85,19 +98,23 @@ impl<'a> Test<'a> {
   }
 
   fn command(&self) -> Result<Command> {
-    let mut command = Command::new(executable_path(env!("CARGO_PKG_NAME")));
-
-    let editor_contents = self
-      .operations
-      .iter()
-      .filter_map(|operation| operation.destination)
-      .collect::<Vec<_>>()
-      .join("\n");
-
-    let editor = Self::editor(&self.tempdir, &editor_contents)?;
+    let mut command = Self::edmv(&self.tempdir);
+
+    let editor_contents = self.edit.clone().unwrap_or_else(|| {
+      self
+        .operations
+        .iter()
+        .filter_map(|operation| operation.destination)
+        .collect::<Vec<_>>()
+        .join("\n")
+    });
+
+    let editor = match &self.editor_command {
+      Some(editor) => PathBuf::from(editor),
+      None => Self::editor(&self.tempdir, &editor_contents)?,
+    };
 
     command
-      .current_dir(&self.tempdir)
       .args(self.operations.iter().map(|path| path.source))
       .arg("--editor")
       .arg(&editor)
@@ -119,13 +136,22 @@ impl<'a> Test<'a> {
     Ok(self)
   }
 
+  fn edit(self, contents: &str) -> Self {
+    Self {
+      edit: Some(contents.unindent().trim_end().to_owned()),
+      ..self
+    }
+  }
+
   #[cfg(unix)]
   fn editor(tempdir: &TempDir, contents: &str) -> Result<PathBuf> {
     let editor = tempdir.path().join("editor.sh");
 
     fs::write(
       &editor,
-      format!("#!/bin/bash\necho -e \"{contents}\" > \"$1\""),
+      format!(
+        "#!/bin/bash\ncp \"$1\" \"$EDMV_TEST_BUFFER\"\necho -e \"{contents}\" > \"$1\""
+      ),
     )?;
 
     fs::set_permissions(&editor, Permissions::from_mode(0o755))?;
@@ -176,6 +202,28 @@ impl<'a> Test<'a> {
     Ok(binary)
   }
 
+  fn editor_command(self, editor_command: &str) -> Self {
+    Self {
+      editor_command: Some(editor_command.to_owned()),
+      ..self
+    }
+  }
+
+  /// Returns a command running `edmv` in `tempdir`, which also holds its
+  /// configuration and state directories and the copy of the buffer it
+  /// edits, so that it never touches those of the user.
+  fn edmv(tempdir: &TempDir) -> Command {
+    let mut command = Command::new(executable_path(env!("CARGO_PKG_NAME")));
+
+    command
+      .current_dir(tempdir)
+      .env("EDMV_TEST_BUFFER", tempdir.path().join("buffer.txt"))
+      .env("XDG_CONFIG_HOME", tempdir.path().join(".config"))
+      .env("XDG_STATE_HOME", tempdir.path().join(".state"));
+
+    command
+  }
+
   fn exists(self, exists: &[&'a str]) -> Self {
     Self {
       exists: exists.to_vec(),
@@ -183,6 +231,13 @@ impl<'a> Test<'a> {
     }
   }
 
+  fn expected_buffer(self, expected_buffer: &str) -> Self {
+    Self {
+      expected_buffer: Some(Self::normalize_expected_text(expected_buffer)),
+      ..self
+    }
+  }
+
   fn expected_status(self, expected_status: i32) -> Self {
     Self {
       expected_status,
@@ -207,7 +262,10 @@ impl<'a> Test<'a> {
   fn new() -> Result<Self> {
     Ok(Self {
       arguments: Vec::new(),
+      edit: None,
+      editor_command: None,
       exists: Vec::new(),
+      expected_buffer: None,
       expected_status: 0,
       expected_stderr: String::new(),
       expected_stdout: String::new(),
@@ -237,6 +295,16 @@ impl<'a> Test<'a> {
     }
   }
 
+  #[cfg(unix)]
+  fn permissions(self, path: &str, mode: u32) -> Result<Self> {
+    fs::set_permissions(
+      self.tempdir.path().join(path),
+      Permissions::from_mode(mode),
+    )?;
+
+    Ok(self)
+  }
+
   fn run(self) -> Result {
     self.run_and_return_tempdir().map(|_| ())
   }
@@ -259,6 +327,13 @@ impl<'a> Test<'a> {
       self.expected_stdout
     );
 
+    if let Some(expected_buffer) = &self.expected_buffer {
+      assert_eq!(
+        fs::read_to_string(self.tempdir.path().join("buffer.txt"))?,
+        *expected_buffer
+      );
+    }
+
     let sources = self
       .operations
       .iter()
@@ -293,6 +368,17 @@ impl<'a> Test<'a> {
 
     Ok(self.tempdir)
   }
+
+  #[cfg(unix)]
+  fn symlink(self, path: &str, target: &str) -> Result<Self> {
+    std::os::unix::fs::symlink(target, self.tempdir.path().join(path))?;
+    Ok(self)
+  }
+
+  fn write(self, path: &str, contents: &str) -> Result<Self> {
+    fs::write(self.tempdir.path().join(path), contents)?;
+    Ok(self)
+  }
 }
 
 #[test]
@@ -390,7 +476,7 @@ fn gives_error_for_existing_destinations() -> Result {
       },
     ])
     .exists(&["a.txt", "b.txt", "c.txt", "d.txt", "e.txt"])
-    .expected_status(1)
+    .expected_status(9)
     .expected_stderr(
       "
       error: Found destination(s) that already exist: d.txt, e.txt, use --force to overwrite
@@ -463,6 +549,9 @@ fn dry_run_works() -> Result {
     .expected_status(0)
     .expected_stdout(
       "
+      rename(\"a.txt\", \"d.txt\")
+      rename(\"b.txt\", \"e.txt\")
+      rename(\"c.txt\", \"f.txt\")
       a.txt -> d.txt
       b.txt -> e.txt
       c.txt -> f.txt
@@ -485,7 +574,7 @@ fn errors_when_passed_invalid_operations() -> Result {
         destination: Some("b.txt"),
       },
     ])
-    .expected_status(1)
+    .expected_status(5)
     .expected_stderr(
       "
       error: Found non-existent path(s): a.txt, c.txt
@@ -521,7 +610,7 @@ fn disallow_duplicate_operations() -> Result {
       },
     ])
     .exists(&["a.txt", "b.txt", "e.txt"])
-    .expected_status(1)
+    .expected_status(8)
     .expected_stderr(
       "
       error: Found duplicate destination(s): c.txt, f.txt
@@ -617,10 +706,10 @@ fn gives_error_for_invalid_destination_directory() -> Result {
       },
     ])
     .exists(&["a.txt", "b.txt"])
-    .expected_status(1)
+    .expected_status(12)
     .expected_stderr(
       "
-      error: Found destination(s) placed within a non-existent directory: foo/a.txt, bar/baz/c.txt
+      error: Found destination(s) placed within a non-existent directory: bar/baz/c.txt, foo/a.txt
       ",
     )
     .run()
@@ -742,7 +831,7 @@ fn destination_count_mismatch() -> Result {
       },
     ])
     .exists(&["a.txt", "b.txt"])
-    .expected_status(1)
+    .expected_status(6)
     .expected_stderr(
       "
       error: Destination count mismatch, should be 2 but received 1
@@ -772,7 +861,7 @@ fn gives_error_for_directory_to_file_operations() -> Result {
       },
     ])
     .exists(&["a", "b", "a.txt", "b.txt"])
-    .expected_status(1)
+    .expected_status(11)
     .expected_stderr(
       "
       error: Found directory to file operation(s): a -> a.txt, b -> b.txt
@@ -797,7 +886,7 @@ fn detects_conflicts() -> Result {
       },
     ])
     .exists(&["a.txt", "b.txt"])
-    .expected_status(1)
+    .expected_status(10)
     .expected_stderr(
       "
       error: Found conflicting operation(s): a.txt -> b.txt, b.txt -> a.txt, use --resolve to properly handle the conflicts
@@ -851,3 +940,2560 @@ fn ignores_self_renames_as_duplicates() -> Result {
     )
     .run()
 }
+
+#[test]
+fn writes_instructional_header() -> Result {
+  Test::new()?
+    .argument("--resolve")
+    .create(&[Path::File("a.txt"), Path::File("#b.txt")])?
+    .operations(&[
+      Operation {
+        source: "a.txt",
+        destination: Some("a.txt"),
+      },
+      Operation {
+        source: "#b.txt",
+        destination: Some("\\#b.txt"),
+      },
+    ])
+    .exists(&["a.txt", "#b.txt"])
+    .expected_status(0)
+    .expected_buffer(
+      "
+      # Edit the paths below to rename them, one path per line.
+      #
+      # Lines starting with `#` are ignored. To name a path that starts
+      # with `#` or `\\`, prefix it with `\\`.
+      #
+      # --force: off, --resolve: on, --dry-run: off
+      #
+      # To abort, delete every path or exit the editor with a non-zero status.
+      a.txt
+      \\#b.txt
+      ",
+    )
+    .expected_stdout(
+      "
+      0 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn ignores_comment_lines() -> Result {
+  Test::new()?
+    .create(&[Path::File("a.txt"), Path::File("b.txt")])?
+    .operations(&[
+      Operation {
+        source: "a.txt",
+        destination: Some("# a comment\nc.txt"),
+      },
+      Operation {
+        source: "b.txt",
+        destination: Some("# another comment\nd.txt"),
+      },
+    ])
+    .exists(&["c.txt", "d.txt"])
+    .expected_status(0)
+    .expected_stdout(
+      "
+      a.txt -> c.txt
+      b.txt -> d.txt
+      2 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn unescapes_paths_starting_with_comment_character() -> Result {
+  Test::new()?
+    .create(&[Path::File("a.txt")])?
+    .operations(&[Operation {
+      source: "a.txt",
+      destination: Some("\\\\#a.txt"),
+    }])
+    .exists(&["#a.txt"])
+    .expected_status(0)
+    .expected_stdout(
+      "
+      a.txt -> #a.txt
+      1 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn aborts_on_empty_buffer() -> Result {
+  Test::new()?
+    .create(&[Path::File("a.txt"), Path::File("b.txt")])?
+    .operations(&[
+      Operation {
+        source: "a.txt",
+        destination: Some("# nothing to see here"),
+      },
+      Operation {
+        source: "b.txt",
+        destination: None,
+      },
+    ])
+    .exists(&["a.txt", "b.txt"])
+    .expected_status(4)
+    .expected_stderr(
+      "
+      error: Buffer is empty, aborting
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn preserves_whitespace_at_edges_of_destinations() -> Result {
+  Test::new()?
+    .create(&[Path::File("a.txt"), Path::File("b.txt")])?
+    .operations(&[
+      Operation {
+        source: "a.txt",
+        destination: Some(" c.txt"),
+      },
+      Operation {
+        source: "b.txt",
+        destination: Some("d.txt "),
+      },
+    ])
+    .exists(&[" c.txt", "d.txt "])
+    .expected_status(0)
+    .expected_stdout(
+      "
+      a.txt ->  c.txt
+      b.txt -> d.txt 
+      2 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn gives_error_for_empty_destinations() -> Result {
+  Test::new()?
+    .create(&[Path::File("a.txt"), Path::File("b.txt")])?
+    .operations(&[
+      Operation {
+        source: "a.txt",
+        destination: Some("\\nc.txt"),
+      },
+      Operation {
+        source: "b.txt",
+        destination: None,
+      },
+    ])
+    .exists(&["a.txt", "b.txt"])
+    .expected_status(7)
+    .expected_stderr(
+      "
+      error: Found empty destination(s) for: a.txt
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn quotes_whitespace_when_showing_whitespace() -> Result {
+  Test::new()?
+    .argument("--show-whitespace")
+    .create(&[Path::File("a.txt "), Path::File("\"b\".txt")])?
+    .operations(&[
+      Operation {
+        source: "a.txt ",
+        destination: Some("\\\"c.txt \\\""),
+      },
+      Operation {
+        source: "\"b\".txt",
+        destination: Some("\\\\\\\"b\\\".txt"),
+      },
+    ])
+    .exists(&["c.txt ", "\"b\".txt"])
+    .expected_status(0)
+    .expected_buffer(
+      "
+      # Edit the paths below to rename them, one path per line.
+      #
+      # Lines starting with `#` are ignored. To name a path that starts
+      # with `#` or `\\`, prefix it with `\\`.
+      # Paths wrapped in `\"` keep their leading and trailing whitespace.
+      #
+      # --force: off, --resolve: off, --dry-run: off
+      #
+      # To abort, delete every path or exit the editor with a non-zero status.
+      \"a.txt \"
+      \\\"b\".txt
+      ",
+    )
+    .expected_stdout(
+      "
+      a.txt  -> c.txt 
+      1 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn renames_with_dual_format() -> Result {
+  Test::new()?
+    .argument("--format")
+    .argument("dual")
+    .create(&[Path::File("a.txt"), Path::File("b.txt")])?
+    .operations(&[
+      Operation {
+        source: "a.txt",
+        destination: Some("b.txt\\td.txt"),
+      },
+      Operation {
+        source: "b.txt",
+        destination: Some("a.txt\\tc.txt"),
+      },
+    ])
+    .exists(&["c.txt", "d.txt"])
+    .expected_status(0)
+    .expected_buffer(
+      "
+      # Edit the destination column below to rename paths. Each line holds
+      # a source and its destination separated by a tab. Lines may be
+      # reordered, but the source column must not change.
+      #
+      # Lines starting with `#` are ignored. To name a path that starts
+      # with `#` or `\\`, prefix it with `\\`.
+      #
+      # --force: off, --resolve: off, --dry-run: off
+      #
+      # To abort, delete every path or exit the editor with a non-zero status.
+      a.txt\ta.txt
+      b.txt\tb.txt
+      ",
+    )
+    .expected_stdout(
+      "
+      a.txt -> c.txt
+      b.txt -> d.txt
+      2 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn gives_error_for_changed_sources_with_dual_format() -> Result {
+  Test::new()?
+    .argument("--format")
+    .argument("dual")
+    .create(&[Path::File("a.txt"), Path::File("b.txt")])?
+    .operations(&[
+      Operation {
+        source: "a.txt",
+        destination: Some("x.txt\\tc.txt"),
+      },
+      Operation {
+        source: "b.txt",
+        destination: Some("b.txt\\td.txt"),
+      },
+    ])
+    .exists(&["a.txt", "b.txt"])
+    .expected_status(7)
+    .expected_stderr(
+      "
+      error: Found source(s) that were changed or added: x.txt, only the destination column may be edited
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn gives_error_for_missing_sources_with_dual_format() -> Result {
+  Test::new()?
+    .argument("--format")
+    .argument("dual")
+    .create(&[Path::File("a.txt"), Path::File("b.txt")])?
+    .operations(&[
+      Operation {
+        source: "a.txt",
+        destination: Some("a.txt\\tc.txt"),
+      },
+      Operation {
+        source: "b.txt",
+        destination: None,
+      },
+    ])
+    .exists(&["a.txt", "b.txt"])
+    .expected_status(7)
+    .expected_stderr(
+      "
+      error: Found source(s) missing from the buffer: b.txt
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn gives_error_for_repeated_sources_with_dual_format() -> Result {
+  Test::new()?
+    .argument("--format")
+    .argument("dual")
+    .create(&[Path::File("a.txt"), Path::File("b.txt")])?
+    .operations(&[
+      Operation {
+        source: "a.txt",
+        destination: Some("a.txt\\tc.txt"),
+      },
+      Operation {
+        source: "b.txt",
+        destination: Some("a.txt\\td.txt"),
+      },
+    ])
+    .exists(&["a.txt", "b.txt"])
+    .expected_status(7)
+    .expected_stderr(
+      "
+      error: Found source(s) listed more than once: a.txt
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn gives_error_for_lines_without_separator_with_dual_format() -> Result {
+  Test::new()?
+    .argument("--format")
+    .argument("dual")
+    .create(&[Path::File("a.txt")])?
+    .operations(&[Operation {
+      source: "a.txt",
+      destination: Some("c.txt"),
+    }])
+    .exists(&["a.txt"])
+    .expected_status(7)
+    .expected_stderr(
+      "
+      error: Found line(s) without a tab separator: c.txt
+      ",
+    )
+    .run()
+}
+
+#[cfg(unix)]
+#[test]
+fn gives_error_for_failing_editor() -> Result {
+  Test::new()?
+    .editor_command("false")
+    .create(&[Path::File("a.txt")])?
+    .operations(&[Operation {
+      source: "a.txt",
+      destination: Some("b.txt"),
+    }])
+    .exists(&["a.txt"])
+    .expected_status(3)
+    .expected_stderr(
+      "
+      error: Failed to open temporary file in editor
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn reports_all_validation_problems_at_once() -> Result {
+  Test::new()?
+    .create(&[
+      Path::Directory("a"),
+      Path::File("b.txt"),
+      Path::File("c.txt"),
+      Path::File("d.txt"),
+      Path::File("e.txt"),
+      Path::File("f.txt"),
+    ])?
+    .operations(&[
+      Operation {
+        source: "a",
+        destination: Some("f.txt"),
+      },
+      Operation {
+        source: "b.txt",
+        destination: Some("c.txt"),
+      },
+      Operation {
+        source: "c.txt",
+        destination: Some("x.txt"),
+      },
+      Operation {
+        source: "d.txt",
+        destination: Some("x.txt"),
+      },
+      Operation {
+        source: "e.txt",
+        destination: Some("foo/e.txt"),
+      },
+    ])
+    .exists(&["a", "b.txt", "c.txt", "d.txt", "e.txt", "f.txt"])
+    .expected_status(13)
+    .expected_stderr(
+      "
+      error: Found 5 kinds of problem:
+        Found duplicate destination(s): x.txt
+        Found destination(s) that already exist: c.txt, f.txt, use --force to overwrite
+        Found conflicting operation(s): b.txt -> c.txt, use --resolve to properly handle the conflicts
+        Found directory to file operation(s): a -> f.txt
+        Found destination(s) placed within a non-existent directory: foo/e.txt
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn dry_run_detects_failing_renames() -> Result {
+  Test::new()?
+    .argument("--dry-run")
+    .create(&[Path::File("a.txt"), Path::File("b.txt")])?
+    .operations(&[Operation {
+      source: "a.txt",
+      destination: Some("b.txt/a.txt"),
+    }])
+    .exists(&["a.txt", "b.txt"])
+    .expected_status(1)
+    .expected_stdout(
+      "
+      rename(\"a.txt\", \"b.txt/a.txt\")
+      ",
+    )
+    .expected_stderr(
+      "
+      error: Failed to rename `a.txt` to `b.txt/a.txt`: not a directory
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn dry_run_detects_collisions_between_stages() -> Result {
+  Test::new()?
+    .argument("--dry-run")
+    .argument("--force")
+    .create(&[
+      Path::Directory("b"),
+      Path::File("a.txt"),
+      Path::File("c.txt"),
+    ])?
+    .operations(&[
+      Operation {
+        source: "a.txt",
+        destination: Some("b"),
+      },
+      Operation {
+        source: "c.txt",
+        destination: Some("b/a.txt"),
+      },
+    ])
+    .exists(&["a.txt", "b", "c.txt"])
+    .expected_status(1)
+    .expected_stdout(
+      "
+      rename(\"a.txt\", \"b/a.txt\")
+      rename(\"c.txt\", \"b/a.txt\")
+      ",
+    )
+    .expected_stderr(
+      "
+      error: Failed to rename `c.txt` to `b/a.txt`: destination was created by an earlier rename
+      ",
+    )
+    .run()
+}
+
+#[cfg(unix)]
+#[test]
+fn dry_run_detects_permission_problems() -> Result {
+  let tempdir = Test::new()?
+    .argument("--dry-run")
+    .create(&[Path::Directory("locked"), Path::File("locked/a.txt")])?
+    .permissions("locked", 0o555)?
+    .operations(&[Operation {
+      source: "locked/a.txt",
+      destination: Some("locked/b.txt"),
+    }])
+    .exists(&["locked/a.txt"])
+    .expected_status(1)
+    .expected_stdout(
+      "
+      rename(\"locked/a.txt\", \"locked/b.txt\")
+      ",
+    )
+    .expected_stderr(
+      "
+      error: Failed to rename `locked/a.txt` to `locked/b.txt`: permission denied
+      ",
+    )
+    .run_and_return_tempdir()?;
+
+  fs::set_permissions(
+    tempdir.path().join("locked"),
+    Permissions::from_mode(0o755),
+  )?;
+
+  Ok(())
+}
+
+#[test]
+fn swaps_directories_with_resolve() -> Result {
+  let tempdir = Test::new()?
+    .argument("--force")
+    .argument("--resolve")
+    .create(&[
+      Path::Directory("a"),
+      Path::Directory("b"),
+      Path::File("a/x.txt"),
+      Path::File("b/y.txt"),
+    ])?
+    .operations(&[
+      Operation {
+        source: "a",
+        destination: Some("b"),
+      },
+      Operation {
+        source: "b",
+        destination: Some("a"),
+      },
+    ])
+    .exists(&["a", "b", "a/y.txt", "b/x.txt"])
+    .expected_status(0)
+    .expected_stdout(
+      "
+      a -> b
+      b -> a
+      2 path(s) changed
+      ",
+    )
+    .run_and_return_tempdir()?;
+
+  assert!(!tempdir.path().join("a/x.txt").exists());
+  assert!(!tempdir.path().join("b/a").exists());
+
+  Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn runs_hooks_around_renames() -> Result {
+  Test::new()?
+    .argument("--before")
+    .argument("echo before $EDMV_COUNT")
+    .argument("--pre-hook")
+    .argument("echo pre $EDMV_SOURCE $EDMV_DESTINATION")
+    .argument("--post-hook")
+    .argument("echo post $1 $2")
+    .argument("--after")
+    .argument("echo after $EDMV_COUNT")
+    .create(&[Path::File("a.txt"), Path::File("b.txt")])?
+    .operations(&[
+      Operation {
+        source: "a.txt",
+        destination: Some("c.txt"),
+      },
+      Operation {
+        source: "b.txt",
+        destination: Some("d.txt"),
+      },
+    ])
+    .exists(&["c.txt", "d.txt"])
+    .expected_status(0)
+    .expected_stdout(
+      "
+      before 2
+      pre a.txt c.txt
+      pre b.txt d.txt
+      a.txt -> c.txt
+      post a.txt c.txt
+      b.txt -> d.txt
+      post b.txt d.txt
+      2 path(s) changed
+      after 2
+      ",
+    )
+    .run()
+}
+
+#[cfg(unix)]
+#[test]
+fn aborts_when_pre_hook_fails() -> Result {
+  Test::new()?
+    .argument("--pre-hook")
+    .argument("test $1 != b.txt")
+    .create(&[Path::File("a.txt"), Path::File("b.txt")])?
+    .operations(&[
+      Operation {
+        source: "a.txt",
+        destination: Some("c.txt"),
+      },
+      Operation {
+        source: "b.txt",
+        destination: Some("d.txt"),
+      },
+    ])
+    .exists(&["a.txt", "b.txt"])
+    .expected_status(14)
+    .expected_stderr(
+      "
+      error: Hook `test $1 != b.txt` failed with exit status: 1
+      ",
+    )
+    .run()
+}
+
+#[cfg(unix)]
+#[test]
+fn skips_renames_when_pre_hook_fails() -> Result {
+  Test::new()?
+    .argument("--pre-hook")
+    .argument("test $1 != b.txt")
+    .argument("--pre-hook-failure")
+    .argument("skip")
+    .create(&[Path::File("a.txt"), Path::File("b.txt")])?
+    .operations(&[
+      Operation {
+        source: "a.txt",
+        destination: Some("c.txt"),
+      },
+      Operation {
+        source: "b.txt",
+        destination: Some("d.txt"),
+      },
+    ])
+    .exists(&["c.txt", "b.txt"])
+    .expected_status(0)
+    .expected_stderr(
+      "
+      warning: Hook `test $1 != b.txt` failed with exit status: 1, skipping b.txt -> d.txt
+      ",
+    )
+    .expected_stdout(
+      "
+      a.txt -> c.txt
+      1 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[cfg(unix)]
+#[test]
+fn revalidates_after_skipping_renames() -> Result {
+  Test::new()?
+    .argument("--force")
+    .argument("--resolve")
+    .argument("--pre-hook")
+    .argument("test $1 != b.txt")
+    .argument("--pre-hook-failure")
+    .argument("skip")
+    .create(&[Path::File("a.txt"), Path::File("b.txt")])?
+    .operations(&[
+      Operation {
+        source: "a.txt",
+        destination: Some("b.txt"),
+      },
+      Operation {
+        source: "b.txt",
+        destination: Some("a.txt"),
+      },
+    ])
+    .exists(&["a.txt", "b.txt"])
+    .expected_status(14)
+    .expected_stderr(
+      "
+      warning: Hook `test $1 != b.txt` failed with exit status: 1, skipping b.txt -> a.txt
+      error: Found destination(s) that still exist because their own rename was skipped: b.txt
+      ",
+    )
+    .run()
+}
+
+#[cfg(unix)]
+#[test]
+fn reports_failing_post_hooks() -> Result {
+  Test::new()?
+    .argument("--post-hook")
+    .argument("false")
+    .create(&[Path::File("a.txt")])?
+    .operations(&[Operation {
+      source: "a.txt",
+      destination: Some("b.txt"),
+    }])
+    .exists(&["b.txt"])
+    .expected_status(14)
+    .expected_stderr(
+      "
+      warning: Hook `false` failed with exit status: 1
+      error: 1 post-hook(s) failed after renaming
+      ",
+    )
+    .expected_stdout(
+      "
+      a.txt -> b.txt
+      1 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[cfg(unix)]
+#[test]
+fn reports_progress_as_lines() -> Result {
+  let tempdir = TempDir::new()?;
+
+  for path in ["a.txt", "b.txt"] {
+    File::create(tempdir.path().join(path))?;
+  }
+
+  let output = Test::edmv(&tempdir)
+    .args(["a.txt", "b.txt", "--progress", "lines"])
+    .args(["--post-hook", "sleep 1", "--editor"])
+    .arg(Test::editor(&tempdir, "c.txt\\nd.txt")?)
+    .output()?;
+
+  assert!(output.status.success());
+
+  assert_eq!(
+    str::from_utf8(&output.stdout)?,
+    "a.txt -> c.txt\nb.txt -> d.txt\n2 path(s) changed\n"
+  );
+
+  let stderr = str::from_utf8(&output.stderr)?;
+
+  let lines = stderr.lines().collect::<Vec<&str>>();
+
+  assert_eq!(lines.len(), 2, "{stderr}");
+
+  for line in lines {
+    assert!(line.starts_with("progress done=2 total=2 rate="), "{line}");
+    assert!(line.ends_with(" eta=0 path=b.txt"), "{line}");
+  }
+
+  Ok(())
+}
+
+#[test]
+fn updates_references_to_renamed_files() -> Result {
+  let tempdir = Test::new()?
+    .argument("--update-references")
+    .argument("**/*.md")
+    .create(&[Path::Directory("docs"), Path::File("docs/setup.md")])?
+    .write("README.md", "See docs/setup.md, or docs/setupx.md.\n")?
+    .write(
+      "docs/index.md",
+      "[Setup](setup.md)\n<a href=\"./setup.md#intro\">setup</a>\n",
+    )?
+    .operations(&[Operation {
+      source: "docs/setup.md",
+      destination: Some("docs/install.md"),
+    }])
+    .exists(&["docs/install.md"])
+    .expected_status(0)
+    .expected_stdout(
+      "
+      docs/setup.md -> docs/install.md
+      README.md:1: docs/setup.md -> docs/install.md
+      docs/index.md:1: setup.md -> install.md
+      docs/index.md:2: ./setup.md -> ./install.md
+      1 path(s) changed
+      3 reference(s) updated
+      ",
+    )
+    .run_and_return_tempdir()?;
+
+  assert_eq!(
+    fs::read_to_string(tempdir.path().join("README.md"))?,
+    "See docs/install.md, or docs/setupx.md.\n"
+  );
+
+  assert_eq!(
+    fs::read_to_string(tempdir.path().join("docs/index.md"))?,
+    "[Setup](install.md)\n<a href=\"./install.md#intro\">setup</a>\n"
+  );
+
+  Ok(())
+}
+
+#[test]
+fn updates_references_within_moved_files() -> Result {
+  let tempdir = Test::new()?
+    .argument("--update-references")
+    .argument("docs/*.md")
+    .create(&[
+      Path::Directory("docs"),
+      Path::Directory("guide"),
+      Path::File("docs/setup.md"),
+    ])?
+    .write("docs/index.md", "[Setup](setup.md) [Missing](missing.md)\n")?
+    .operations(&[Operation {
+      source: "docs/index.md",
+      destination: Some("guide/index.md"),
+    }])
+    .exists(&["guide/index.md"])
+    .expected_status(0)
+    .expected_stdout(
+      "
+      docs/index.md -> guide/index.md
+      docs/index.md:1: setup.md -> ../docs/setup.md
+      1 path(s) changed
+      1 reference(s) updated
+      ",
+    )
+    .run_and_return_tempdir()?;
+
+  assert_eq!(
+    fs::read_to_string(tempdir.path().join("guide/index.md"))?,
+    "[Setup](../docs/setup.md) [Missing](missing.md)\n"
+  );
+
+  Ok(())
+}
+
+#[test]
+fn dry_run_shows_reference_updates() -> Result {
+  let tempdir = Test::new()?
+    .argument("--dry-run")
+    .argument("--update-references")
+    .argument("*.md")
+    .create(&[Path::Directory("a")])?
+    .write("README.md", "Read a/b.txt\n")?
+    .operations(&[Operation {
+      source: "a",
+      destination: Some("c"),
+    }])
+    .exists(&["a"])
+    .expected_status(0)
+    .expected_stdout(
+      "
+      rename(\"a\", \"c\")
+      a -> c
+      README.md:1: a/b.txt -> c/b.txt
+      0 path(s) changed
+      0 reference(s) updated
+      ",
+    )
+    .run_and_return_tempdir()?;
+
+  assert_eq!(
+    fs::read_to_string(tempdir.path().join("README.md"))?,
+    "Read a/b.txt\n"
+  );
+
+  Ok(())
+}
+
+#[test]
+fn gives_error_for_invalid_reference_patterns() -> Result {
+  Test::new()?
+    .argument("--update-references")
+    .argument("[")
+    .create(&[Path::File("a.txt")])?
+    .operations(&[Operation {
+      source: "a.txt",
+      destination: Some("b.txt"),
+    }])
+    .exists(&["a.txt"])
+    .expected_status(15)
+    .expected_stderr(
+      "
+      error: Invalid reference pattern `[`: Pattern syntax error near position 0: invalid range pattern
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn renames_companions_with_extensions() -> Result {
+  let tempdir = Test::new()?
+    .argument("--companions")
+    .argument("xmp")
+    .create(&[
+      Path::File("IMG_001.CR2"),
+      Path::File("IMG_001.CR2.xmp"),
+      Path::File("IMG_001.jpg"),
+      Path::File("IMG_001.xmp"),
+      Path::File("IMG_0010.xmp"),
+    ])?
+    .operations(&[Operation {
+      source: "IMG_001.CR2",
+      destination: Some("trip.CR2"),
+    }])
+    .exists(&["trip.CR2"])
+    .expected_status(0)
+    .expected_stdout(
+      "
+      IMG_001.CR2 -> trip.CR2
+      IMG_001.CR2.xmp -> trip.CR2.xmp
+      IMG_001.xmp -> trip.xmp
+      3 path(s) changed
+      ",
+    )
+    .run_and_return_tempdir()?;
+
+  for path in ["trip.CR2.xmp", "trip.xmp", "IMG_001.jpg", "IMG_0010.xmp"] {
+    assert!(tempdir.path().join(path).is_file(), "{path} is missing");
+  }
+
+  Ok(())
+}
+
+#[test]
+fn renames_companions_with_any_extension() -> Result {
+  let tempdir = Test::new()?
+    .argument("--force")
+    .argument("--companions")
+    .argument("auto")
+    .create(&[
+      Path::Directory("films"),
+      Path::File("movie.en.srt"),
+      Path::File("movie.mkv"),
+      Path::File("movie.nfo"),
+    ])?
+    .operations(&[Operation {
+      source: "movie.mkv",
+      destination: Some("films"),
+    }])
+    .exists(&["films"])
+    .expected_status(0)
+    .expected_stdout(
+      "
+      movie.mkv -> films/movie.mkv
+      movie.en.srt -> films/movie.en.srt
+      movie.nfo -> films/movie.nfo
+      3 path(s) changed
+      ",
+    )
+    .run_and_return_tempdir()?;
+
+  for path in ["films/movie.mkv", "films/movie.en.srt", "films/movie.nfo"] {
+    assert!(tempdir.path().join(path).is_file(), "{path} is missing");
+  }
+
+  Ok(())
+}
+
+#[test]
+fn validates_companion_renames() -> Result {
+  Test::new()?
+    .argument("--companions")
+    .argument("srt")
+    .create(&[Path::File("a.mkv"), Path::File("a.srt"), Path::File("b.srt")])?
+    .operations(&[Operation {
+      source: "a.mkv",
+      destination: Some("b.mkv"),
+    }])
+    .exists(&["a.mkv"])
+    .expected_status(9)
+    .expected_stderr(
+      "
+      error: Found destination(s) that already exist: b.srt, use --force to overwrite
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn gives_error_for_ambiguous_companions() -> Result {
+  Test::new()?
+    .argument("--companions")
+    .argument("xmp")
+    .create(&[
+      Path::File("IMG.CR2"),
+      Path::File("IMG.jpg"),
+      Path::File("IMG.xmp"),
+    ])?
+    .operations(&[
+      Operation {
+        source: "IMG.CR2",
+        destination: Some("raw.CR2"),
+      },
+      Operation {
+        source: "IMG.jpg",
+        destination: Some("edit.jpg"),
+      },
+    ])
+    .exists(&["IMG.CR2", "IMG.jpg"])
+    .expected_status(16)
+    .expected_stderr(
+      "
+      error: Found companion(s) shared by files renamed to different stems: IMG.xmp
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn edits_basenames_only() -> Result {
+  let tempdir = Test::new()?
+    .argument("--basename")
+    .create(&[Path::Directory("dir"), Path::File("dir/a.txt")])?
+    .operations(&[Operation {
+      source: "dir/a.txt",
+      destination: Some("b.md"),
+    }])
+    .expected_status(0)
+    .expected_buffer(
+      "
+      # Edit the paths below to rename them, one path per line.
+      #
+      # Lines starting with `#` are ignored. To name a path that starts
+      # with `#` or `\\`, prefix it with `\\`.
+      # Only the final component of each path is shown, and may not contain `/`.
+      #
+      # --force: off, --resolve: off, --dry-run: off
+      #
+      # To abort, delete every path or exit the editor with a non-zero status.
+      a.txt
+      ",
+    )
+    .expected_stdout(
+      "
+      dir/a.txt -> dir/b.md
+      1 path(s) changed
+      ",
+    )
+    .run_and_return_tempdir()?;
+
+  assert!(tempdir.path().join("dir/b.md").is_file());
+
+  Ok(())
+}
+
+#[test]
+fn edits_stems_only() -> Result {
+  let tempdir = Test::new()?
+    .argument("--stem")
+    .create(&[
+      Path::Directory("dir"),
+      Path::File("dir/photo.jpg"),
+      Path::File("dir/.hidden"),
+    ])?
+    .operations(&[
+      Operation {
+        source: "dir/photo.jpg",
+        destination: Some("holiday"),
+      },
+      Operation {
+        source: "dir/.hidden",
+        destination: Some(".shown"),
+      },
+    ])
+    .expected_status(0)
+    .expected_stdout(
+      "
+      dir/photo.jpg -> dir/holiday.jpg
+      dir/.hidden -> dir/.shown
+      2 path(s) changed
+      ",
+    )
+    .run_and_return_tempdir()?;
+
+  assert!(tempdir.path().join("dir/holiday.jpg").is_file());
+  assert!(tempdir.path().join("dir/.shown").is_file());
+
+  Ok(())
+}
+
+#[test]
+fn keeps_unchanged_directories_with_trailing_separators() -> Result {
+  for scope in ["--basename", "--stem"] {
+    Test::new()?
+      .argument(scope)
+      .create(&[Path::Directory("d"), Path::Directory("d/sub")])?
+      .operations(&[Operation {
+        source: "d/sub/",
+        destination: Some("sub"),
+      }])
+      .exists(&["d/sub/"])
+      .expected_stdout(
+        "
+        0 path(s) changed
+        ",
+      )
+      .run()?;
+  }
+
+  Test::new()?
+    .argument("--basename")
+    .create(&[Path::Directory("d"), Path::Directory("d/sub")])?
+    .operations(&[Operation {
+      source: "d/sub/",
+      destination: Some("new"),
+    }])
+    .exists(&["d/new"])
+    .expected_stdout(
+      "
+      d/sub/ -> d/new/
+      1 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn gives_error_for_separators_in_names() -> Result {
+  Test::new()?
+    .argument("--basename")
+    .create(&[Path::Directory("dir"), Path::File("dir/a.txt")])?
+    .operations(&[Operation {
+      source: "dir/a.txt",
+      destination: Some("other/a.txt"),
+    }])
+    .exists(&["dir/a.txt"])
+    .expected_status(7)
+    .expected_stderr(
+      "
+      error: Found name(s) containing a path separator where only a single component is allowed: other/a.txt
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn validates_basenames_against_directories() -> Result {
+  Test::new()?
+    .argument("--basename")
+    .argument("--force")
+    .create(&[
+      Path::Directory("dir"),
+      Path::Directory("dir/a"),
+      Path::File("dir/b.txt"),
+    ])?
+    .operations(&[Operation {
+      source: "dir/a",
+      destination: Some("b.txt"),
+    }])
+    .exists(&["dir/a"])
+    .expected_status(11)
+    .expected_stderr(
+      "
+      error: Found directory to file operation(s): dir/a -> dir/b.txt
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn keeps_full_sources_in_dual_format_with_stems() -> Result {
+  Test::new()?
+    .argument("--format")
+    .argument("dual")
+    .argument("--stem")
+    .create(&[Path::Directory("dir"), Path::File("dir/a.txt")])?
+    .operations(&[Operation {
+      source: "dir/a.txt",
+      destination: Some("dir/a.txt\\tb"),
+    }])
+    .expected_status(0)
+    .expected_stdout(
+      "
+      dir/a.txt -> dir/b.txt
+      1 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn renames_directories_in_tree_format() -> Result {
+  Test::new()?
+    .argument("--format")
+    .argument("tree")
+    .create(&[Path::Directory("dir"), Path::File("dir/a.txt")])?
+    .operations(&[Operation {
+      source: "dir",
+      destination: None,
+    }])
+    .edit(
+      "
+      1 renamed/
+      2   a.txt
+      ",
+    )
+    .exists(&["renamed/a.txt"])
+    .expected_buffer(
+      "
+      # Edit the names below to rename paths. Each line starts with an ID
+      # that must be kept, and indentation places a path inside the
+      # directory above it. Renaming a directory renames its contents.
+      # Lines without an ID create an empty file, or a directory if they
+      # end with `/`.
+      #
+      # Lines starting with `#` are ignored. To name a path that starts
+      # with `#` or `\\`, prefix it with `\\`.
+      #
+      # --force: off, --resolve: off, --dry-run: off
+      #
+      # To abort, delete every path or exit the editor with a non-zero status.
+      1 dir/
+      2   a.txt
+      ",
+    )
+    .expected_stdout(
+      "
+      dir -> renamed
+      1 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn lists_sources_beneath_later_sources_once_in_tree_format() -> Result {
+  Test::new()?
+    .argument("--format")
+    .argument("tree")
+    .create(&[Path::Directory("dir/sub"), Path::File("dir/sub/a.txt")])?
+    .operations(&[
+      Operation {
+        source: "dir/sub",
+        destination: None,
+      },
+      Operation {
+        source: "dir",
+        destination: None,
+      },
+    ])
+    .edit(
+      "
+      1 dir/
+      2   renamed/
+      3     a.txt
+      ",
+    )
+    .exists(&["dir", "dir/renamed/a.txt"])
+    .expected_buffer(
+      "
+      # Edit the names below to rename paths. Each line starts with an ID
+      # that must be kept, and indentation places a path inside the
+      # directory above it. Renaming a directory renames its contents.
+      # Lines without an ID create an empty file, or a directory if they
+      # end with `/`.
+      #
+      # Lines starting with `#` are ignored. To name a path that starts
+      # with `#` or `\\`, prefix it with `\\`.
+      #
+      # --force: off, --resolve: off, --dry-run: off
+      #
+      # To abort, delete every path or exit the editor with a non-zero status.
+      1 dir/
+      2   sub/
+      3     a.txt
+      ",
+    )
+    .expected_stdout(
+      "
+      dir/sub -> dir/renamed
+      1 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn moves_paths_by_indentation_in_tree_format() -> Result {
+  Test::new()?
+    .argument("--format")
+    .argument("tree")
+    .create(&[
+      Path::Directory("a"),
+      Path::Directory("b"),
+      Path::File("a/x.txt"),
+      Path::File("b/y.txt"),
+    ])?
+    .operations(&[
+      Operation {
+        source: "a",
+        destination: None,
+      },
+      Operation {
+        source: "b",
+        destination: None,
+      },
+    ])
+    .edit(
+      "
+      1 a/
+      3 b/
+      2   x.txt
+      4 y.txt
+      ",
+    )
+    .exists(&["a", "b", "b/x.txt", "y.txt"])
+    .expected_stdout(
+      "
+      a/x.txt -> b/x.txt
+      b/y.txt -> y.txt
+      2 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn renames_within_renamed_directories_in_tree_format() -> Result {
+  Test::new()?
+    .argument("--format")
+    .argument("tree")
+    .create(&[
+      Path::Directory("dir"),
+      Path::Directory("dir/sub"),
+      Path::File("dir/sub/a.txt"),
+    ])?
+    .operations(&[Operation {
+      source: "dir",
+      destination: None,
+    }])
+    .edit(
+      "
+      1 renamed/
+      2   other/
+      3     b.txt
+      ",
+    )
+    .exists(&["renamed/other/b.txt"])
+    .expected_stdout(
+      "
+      dir/sub/a.txt -> dir/sub/b.txt
+      dir/sub -> dir/other
+      dir -> renamed
+      3 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn gives_error_for_malformed_trees() -> Result {
+  for (edit, stderr) in [
+    ("1 dir/\n9   a.txt", "Found unknown ID(s): 9"),
+    ("1 dir/\n2   a.txt\n/", "Found new line(s) without a name: /"),
+    ("1 dir/", "Found source(s) missing from the buffer: dir/a.txt"),
+    (
+      "1 dir/\n2   a.txt\n2   b.txt",
+      "Found source(s) listed more than once: dir/a.txt",
+    ),
+    (
+      "2 a.txt\n1   dir/",
+      "Found path(s) indented beneath a file: dir",
+    ),
+    (
+      "1 dir/\n2   sub/a.txt",
+      "Found name(s) containing a path separator where only a single component is allowed: sub/a.txt",
+    ),
+  ] {
+    Test::new()?
+      .argument("--format")
+      .argument("tree")
+      .create(&[Path::Directory("dir"), Path::File("dir/a.txt")])?
+      .operations(&[Operation {
+        source: "dir",
+        destination: None,
+      }])
+      .edit(edit)
+      .exists(&["dir", "dir/a.txt"])
+      .expected_status(7)
+      .expected_stderr(&format!("error: {stderr}\n"))
+      .run()?;
+  }
+
+  Ok(())
+}
+
+#[test]
+fn gives_error_for_directories_moved_inside_themselves() -> Result {
+  Test::new()?
+    .argument("--format")
+    .argument("tree")
+    .create(&[Path::Directory("dir"), Path::Directory("dir/sub")])?
+    .operations(&[Operation {
+      source: "dir",
+      destination: None,
+    }])
+    .edit(
+      "
+      2 sub/
+      1   dir/
+      ",
+    )
+    .exists(&["dir", "dir/sub"])
+    .expected_status(10)
+    .expected_stderr(
+      "
+      error: Found directory(s) moved inside themselves: dir
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn gives_error_for_nested_renames_with_resolve_in_tree_format() -> Result {
+  Test::new()?
+    .argument("--format")
+    .argument("tree")
+    .argument("--resolve")
+    .create(&[Path::Directory("dir"), Path::File("dir/a.txt")])?
+    .operations(&[Operation {
+      source: "dir",
+      destination: None,
+    }])
+    .edit(
+      "
+      1 renamed/
+      2   b.txt
+      ",
+    )
+    .exists(&["dir", "dir/a.txt"])
+    .expected_status(10)
+    .expected_stderr(
+      "
+      error: Found rename(s) within renamed directories: dir/a.txt, which --resolve cannot perform
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn gives_error_for_tree_format_with_basename() -> Result {
+  Test::new()?
+    .argument("--format")
+    .argument("tree")
+    .argument("--basename")
+    .create(&[Path::File("a.txt")])?
+    .operations(&[Operation {
+      source: "a.txt",
+      destination: Some("1 b.txt"),
+    }])
+    .exists(&["a.txt"])
+    .expected_status(2)
+    .expected_stderr(
+      "
+      error: --format tree cannot be used with --basename, --columns, --show-whitespace, or --stem
+
+      Usage: edmv [OPTIONS] [sources]...
+             edmv <COMMAND>
+
+      For more information, try '--help'.
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn gives_error_for_single_format_with_columns() -> Result {
+  Test::new()?
+    .argument("--format")
+    .argument("single")
+    .argument("--columns")
+    .argument("size")
+    .create(&[Path::File("a.txt")])?
+    .operations(&[Operation {
+      source: "a.txt",
+      destination: Some("0 | b.txt"),
+    }])
+    .exists(&["a.txt"])
+    .expected_status(2)
+    .expected_stderr(
+      "
+      error: --format single cannot be used with --columns, since sorting lines would reorder renames
+
+      Usage: edmv [OPTIONS] [sources]...
+             edmv <COMMAND>
+
+      For more information, try '--help'.
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn gives_error_for_tui_with_editor_options() -> Result {
+  for (arguments, conflict) in [
+    (&["--editor", "true"][..], "--editor <EDITOR>"),
+    (&["--format", "dual"], "--format <FORMAT>"),
+    (&["--basename"], "--basename"),
+    (&["--stem"], "--stem"),
+    (&["--show-whitespace"], "--show-whitespace"),
+    (&["--columns", "size"], "--columns <COLUMNS>"),
+  ] {
+    let tempdir = TempDir::new()?;
+
+    File::create(tempdir.path().join("a.txt"))?;
+
+    let output = Test::edmv(&tempdir)
+      .args(["a.txt", "--tui"])
+      .args(arguments)
+      .output()?;
+
+    assert_eq!(output.status.code(), Some(2));
+
+    let stderr = str::from_utf8(&output.stderr)?;
+
+    assert!(
+      stderr.starts_with(&format!(
+        "error: the argument '--tui' cannot be used with '{conflict}'"
+      )),
+      "{stderr}"
+    );
+
+    assert!(tempdir.path().join("a.txt").exists());
+  }
+
+  Ok(())
+}
+
+#[test]
+fn gives_error_for_tui_without_terminal() -> Result {
+  let tempdir = TempDir::new()?;
+
+  File::create(tempdir.path().join("a.txt"))?;
+
+  let output = Test::edmv(&tempdir).args(["a.txt", "--tui"]).output()?;
+
+  assert_eq!(output.status.code(), Some(20));
+
+  assert_eq!(
+    str::from_utf8(&output.stderr)?,
+    "error: --tui needs stdin and stdout to be a terminal\n"
+  );
+
+  assert!(output.stdout.is_empty());
+
+  assert!(tempdir.path().join("a.txt").exists());
+
+  Ok(())
+}
+
+#[test]
+fn creates_paths_from_new_lines_in_tree_format() -> Result {
+  Test::new()?
+    .argument("--format")
+    .argument("tree")
+    .create(&[Path::Directory("dir"), Path::File("dir/a.txt")])?
+    .operations(&[Operation {
+      source: "dir",
+      destination: None,
+    }])
+    .edit(
+      "
+      1 renamed/
+          docs/
+      2     a.txt
+            README.md
+      notes.txt
+      ",
+    )
+    .exists(&["renamed/docs/a.txt", "renamed/docs/README.md", "notes.txt"])
+    .expected_stdout(
+      "
+      created dir/docs
+      created dir/docs/README.md
+      created notes.txt
+      dir/a.txt -> dir/docs/a.txt
+      dir -> renamed
+      5 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn shows_creations_in_dry_run() -> Result {
+  Test::new()?
+    .argument("--format")
+    .argument("tree")
+    .argument("--dry-run")
+    .create(&[Path::Directory("dir")])?
+    .operations(&[Operation {
+      source: "dir",
+      destination: None,
+    }])
+    .edit(
+      "
+      1 dir/
+          sub/
+            a.txt
+      ",
+    )
+    .exists(&["dir"])
+    .expected_stdout(
+      r#"
+      create_dir("dir/sub")
+      create_file("dir/sub/a.txt")
+      created dir/sub
+      created dir/sub/a.txt
+      0 path(s) changed
+      "#,
+    )
+    .run()
+}
+
+#[test]
+fn validates_creations_against_renames() -> Result {
+  Test::new()?
+    .argument("--format")
+    .argument("tree")
+    .create(&[
+      Path::Directory("dir"),
+      Path::File("dir/a.txt"),
+      Path::File("dir/b.txt"),
+    ])?
+    .operations(&[Operation {
+      source: "dir",
+      destination: None,
+    }])
+    .edit(
+      "
+      1 dir/
+      2   c.txt
+      3   b.txt
+          c.txt
+          b.txt
+      ",
+    )
+    .exists(&["dir", "dir/a.txt", "dir/b.txt"])
+    .expected_status(13)
+    .expected_stderr(
+      "
+      error: Found 2 kinds of problem:
+        Found duplicate destination(s): dir/c.txt
+        Found destination(s) that already exist: dir/b.txt, use --force to overwrite
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn shows_read_only_columns() -> Result {
+  Test::new()?
+    .argument("--columns")
+    .argument("size,type")
+    .create(&[Path::File("a.txt")])?
+    .write("long.txt", "0123456789")?
+    .operations(&[
+      Operation {
+        source: "a.txt",
+        destination: Some("edited | a.txt\\tb.txt"),
+      },
+      Operation {
+        source: "long.txt",
+        destination: Some("10  file | long.txt\\tlong.txt"),
+      },
+    ])
+    .exists(&["b.txt", "long.txt"])
+    .expected_buffer(
+      "
+      # Edit the destination column below to rename paths. Each line holds
+      # a source and its destination separated by a tab. Lines may be
+      # reordered, but the source column must not change.
+      #
+      # Lines starting with `#` are ignored. To name a path that starts
+      # with `#` or `\\`, prefix it with `\\`.
+      # The columns before `|` on each line are read-only and ignored.
+      #
+      # --force: off, --resolve: off, --dry-run: off
+      #
+      # To abort, delete every path or exit the editor with a non-zero status.
+       0  file | a.txt\ta.txt
+      10  file | long.txt\tlong.txt
+      ",
+    )
+    .expected_stdout(
+      "
+      a.txt -> b.txt
+      1 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn sorts_sources_naturally() -> Result {
+  Test::new()?
+    .argument("--sort")
+    .argument("natural")
+    .create(&[
+      Path::File("file10.txt"),
+      Path::File("file2.txt"),
+      Path::File("File1.txt"),
+    ])?
+    .operations(&[
+      Operation {
+        source: "file10.txt",
+        destination: Some("File1.txt"),
+      },
+      Operation {
+        source: "file2.txt",
+        destination: Some("file2.txt"),
+      },
+      Operation {
+        source: "File1.txt",
+        destination: Some("file10.txt"),
+      },
+    ])
+    .exists(&["file10.txt", "file2.txt", "File1.txt"])
+    .expected_buffer(
+      "
+      # Edit the paths below to rename them, one path per line.
+      #
+      # Lines starting with `#` are ignored. To name a path that starts
+      # with `#` or `\\`, prefix it with `\\`.
+      #
+      # --force: off, --resolve: off, --dry-run: off
+      #
+      # To abort, delete every path or exit the editor with a non-zero status.
+      File1.txt
+      file2.txt
+      file10.txt
+      ",
+    )
+    .expected_stdout(
+      "
+      0 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn sorts_sources_by_size_in_reverse() -> Result {
+  Test::new()?
+    .argument("--sort")
+    .argument("size")
+    .argument("--reverse")
+    .write("a.txt", "a")?
+    .write("b.txt", "bbb")?
+    .write("c.txt", "cc")?
+    .operations(&[
+      Operation {
+        source: "a.txt",
+        destination: Some("x.txt"),
+      },
+      Operation {
+        source: "b.txt",
+        destination: Some("y.txt"),
+      },
+      Operation {
+        source: "c.txt",
+        destination: Some("z.txt"),
+      },
+    ])
+    .exists(&["x.txt", "y.txt", "z.txt"])
+    .expected_buffer(
+      "
+      # Edit the paths below to rename them, one path per line.
+      #
+      # Lines starting with `#` are ignored. To name a path that starts
+      # with `#` or `\\`, prefix it with `\\`.
+      #
+      # --force: off, --resolve: off, --dry-run: off
+      #
+      # To abort, delete every path or exit the editor with a non-zero status.
+      b.txt
+      c.txt
+      a.txt
+      ",
+    )
+    .expected_stdout(
+      "
+      b.txt -> x.txt
+      c.txt -> y.txt
+      a.txt -> z.txt
+      3 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn sorts_sources_by_extension() -> Result {
+  Test::new()?
+    .argument("--sort")
+    .argument("ext")
+    .create(&[
+      Path::File("b.jpg"),
+      Path::File("a.png"),
+      Path::File("c.jpg"),
+    ])?
+    .operations(&[
+      Operation {
+        source: "b.jpg",
+        destination: Some("b.jpg"),
+      },
+      Operation {
+        source: "a.png",
+        destination: Some("c.jpg"),
+      },
+      Operation {
+        source: "c.jpg",
+        destination: Some("a.png"),
+      },
+    ])
+    .exists(&["a.png", "b.jpg", "c.jpg"])
+    .expected_buffer(
+      "
+      # Edit the paths below to rename them, one path per line.
+      #
+      # Lines starting with `#` are ignored. To name a path that starts
+      # with `#` or `\\`, prefix it with `\\`.
+      #
+      # --force: off, --resolve: off, --dry-run: off
+      #
+      # To abort, delete every path or exit the editor with a non-zero status.
+      b.jpg
+      c.jpg
+      a.png
+      ",
+    )
+    .expected_stdout(
+      "
+      0 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn filters_sources_by_type_and_extension() -> Result {
+  Test::new()?
+    .argument("--type")
+    .argument("file")
+    .argument("--ext")
+    .argument("JPG")
+    .create(&[
+      Path::File("a.jpg"),
+      Path::File("b.png"),
+      Path::Directory("c.jpg"),
+    ])?
+    .operations(&[
+      Operation {
+        source: "a.jpg",
+        destination: Some("d.jpg"),
+      },
+      Operation {
+        source: "b.png",
+        destination: None,
+      },
+      Operation {
+        source: "c.jpg",
+        destination: None,
+      },
+    ])
+    .exists(&["b.png", "c.jpg", "d.jpg"])
+    .expected_stdout(
+      "
+      a.jpg -> d.jpg
+      1 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn excludes_ignored_paths_from_tree() -> Result {
+  Test::new()?
+    .argument("--format")
+    .argument("tree")
+    .argument("--exclude")
+    .argument("node_modules")
+    .create(&[
+      Path::Directory("dir"),
+      Path::Directory("dir/build"),
+      Path::Directory("dir/node_modules"),
+      Path::File("dir/a.txt"),
+      Path::File("dir/debug.log"),
+      Path::File("dir/keep.log"),
+    ])?
+    .write("dir/.gitignore", "build/\n*.log\n")?
+    .write("dir/.edmvignore", "!keep.log\n.*ignore\n")?
+    .operations(&[Operation {
+      source: "dir",
+      destination: None,
+    }])
+    .edit(
+      "
+      1 dir/
+      2   b.txt
+      3   keep.log
+      ",
+    )
+    .exists(&[
+      "dir",
+      "dir/b.txt",
+      "dir/build",
+      "dir/debug.log",
+      "dir/keep.log",
+    ])
+    .expected_stdout(
+      "
+      dir/a.txt -> dir/b.txt
+      1 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[cfg(unix)]
+#[test]
+fn retargets_moved_symlinks() -> Result {
+  let tempdir = Test::new()?
+    .argument("--fix-symlinks")
+    .argument("--symlink-root")
+    .argument("other")
+    .create(&[
+      Path::Directory("a"),
+      Path::Directory("b"),
+      Path::Directory("other"),
+      Path::File("a/file.txt"),
+    ])?
+    .symlink("a/link", "file.txt")?
+    .symlink("other/rel", "../a/file.txt")?
+    .symlink("other/unrelated", "../b")?
+    .operations(&[
+      Operation {
+        source: "a/link",
+        destination: Some("b/link"),
+      },
+      Operation {
+        source: "a/file.txt",
+        destination: Some("b/moved.txt"),
+      },
+    ])
+    .exists(&["b/link", "b/moved.txt", "other/rel"])
+    .expected_stdout(
+      "
+      a/link -> b/link
+      a/file.txt -> b/moved.txt
+      a/link: file.txt -> moved.txt
+      other/rel: ../a/file.txt -> ../b/moved.txt
+      2 path(s) changed
+      2 link(s) retargeted
+      ",
+    )
+    .run_and_return_tempdir()?;
+
+  assert_eq!(
+    fs::read_link(tempdir.path().join("b/link"))?,
+    PathBuf::from("moved.txt")
+  );
+
+  assert_eq!(
+    fs::read_link(tempdir.path().join("other/rel"))?,
+    PathBuf::from("../b/moved.txt")
+  );
+
+  Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn leaves_symlinks_alone_without_fix_symlinks() -> Result {
+  let tempdir = Test::new()?
+    .create(&[Path::Directory("a"), Path::File("file.txt")])?
+    .symlink("link", "file.txt")?
+    .operations(&[Operation {
+      source: "link",
+      destination: Some("a/link"),
+    }])
+    .expected_stdout(
+      "
+      link -> a/link
+      1 path(s) changed
+      ",
+    )
+    .run_and_return_tempdir()?;
+
+  assert_eq!(
+    fs::read_link(tempdir.path().join("a/link"))?,
+    PathBuf::from("file.txt")
+  );
+
+  Ok(())
+}
+
+#[test]
+fn reads_defaults_from_config_file() -> Result {
+  Test::new()?
+    .argument("--config")
+    .argument("config.toml")
+    .write(
+      "config.toml",
+      "sort = \"natural\"\nreverse = true\ndry-run = true\n",
+    )?
+    .create(&[
+      Path::File("a1.txt"),
+      Path::File("a10.txt"),
+      Path::File("a2.txt"),
+    ])?
+    .operations(&[
+      Operation {
+        source: "a1.txt",
+        destination: Some("b10.txt"),
+      },
+      Operation {
+        source: "a10.txt",
+        destination: Some("b2.txt"),
+      },
+      Operation {
+        source: "a2.txt",
+        destination: Some("b1.txt"),
+      },
+    ])
+    .exists(&["a1.txt", "a10.txt", "a2.txt"])
+    .expected_buffer(
+      "
+      # Edit the paths below to rename them, one path per line.
+      #
+      # Lines starting with `#` are ignored. To name a path that starts
+      # with `#` or `\\`, prefix it with `\\`.
+      #
+      # --force: off, --resolve: off, --dry-run: on
+      #
+      # To abort, delete every path or exit the editor with a non-zero status.
+      a10.txt
+      a2.txt
+      a1.txt
+      ",
+    )
+    .expected_stdout(
+      r#"
+      rename("a10.txt", "b10.txt")
+      rename("a2.txt", "b2.txt")
+      rename("a1.txt", "b1.txt")
+      a10.txt -> b10.txt
+      a2.txt -> b2.txt
+      a1.txt -> b1.txt
+      0 path(s) changed
+      "#,
+    )
+    .run()
+}
+
+#[test]
+fn prefers_command_line_flags_over_presets() -> Result {
+  Test::new()?
+    .argument("--config")
+    .argument("config.toml")
+    .argument("--preset")
+    .argument("ordered")
+    .argument("--sort")
+    .argument("name")
+    .write(
+      "config.toml",
+      "sort = \"size\"\n\n[presets.ordered]\nsort = \"natural\"\nreverse = true\n",
+    )?
+    .create(&[Path::File("a1.txt"), Path::File("a10.txt"), Path::File("a2.txt")])?
+    .operations(&[
+      Operation {
+        source: "a2.txt",
+        destination: Some("a2.txt"),
+      },
+      Operation {
+        source: "a10.txt",
+        destination: Some("a10.txt"),
+      },
+      Operation {
+        source: "a1.txt",
+        destination: Some("a1.txt"),
+      },
+    ])
+    .exists(&["a1.txt", "a10.txt", "a2.txt"])
+    .expected_buffer(
+      "
+      # Edit the paths below to rename them, one path per line.
+      #
+      # Lines starting with `#` are ignored. To name a path that starts
+      # with `#` or `\\`, prefix it with `\\`.
+      #
+      # --force: off, --resolve: off, --dry-run: off
+      #
+      # To abort, delete every path or exit the editor with a non-zero status.
+      a2.txt
+      a10.txt
+      a1.txt
+      ",
+    )
+    .expected_stdout(
+      "
+      0 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn gives_error_for_invalid_config_files() -> Result {
+  for (config, arguments, message) in [
+    ("bogus = true\n", &[][..], "unknown flag `bogus`"),
+    ("resolve = \"yes\"\n", &[], "`resolve` must be a boolean"),
+    ("", &["--preset", "photos"], "no preset named `photos`"),
+  ] {
+    let mut test = Test::new()?
+      .argument("--config")
+      .argument("config.toml")
+      .write("config.toml", config)?
+      .create(&[Path::File("a.txt")])?
+      .operations(&[Operation {
+        source: "a.txt",
+        destination: Some("b.txt"),
+      }])
+      .exists(&["a.txt"])
+      .expected_status(17)
+      .expected_stderr(&format!(
+        "error: Invalid configuration file `config.toml`: {message}\n"
+      ));
+
+    for argument in arguments {
+      test = test.argument(argument);
+    }
+
+    test.run()?;
+  }
+
+  Ok(())
+}
+
+#[test]
+fn prints_shell_completions() -> Result {
+  for shell in ["bash", "elvish", "fish", "powershell", "zsh"] {
+    let output = Command::new(executable_path(env!("CARGO_PKG_NAME")))
+      .args(["completions", shell])
+      .output()?;
+
+    assert!(output.status.success());
+    assert!(str::from_utf8(&output.stdout)?.contains("dry-run"));
+  }
+
+  Ok(())
+}
+
+#[test]
+fn prints_man_page() -> Result {
+  let output = Command::new(executable_path(env!("CARGO_PKG_NAME")))
+    .arg("man")
+    .output()?;
+
+  assert!(output.status.success());
+
+  let man = str::from_utf8(&output.stdout)?;
+
+  assert!(man.contains(".TH edmv 1"));
+  assert!(man.contains("completions"));
+
+  Ok(())
+}
+
+#[test]
+fn lists_stages_with_verbose() -> Result {
+  Test::new()?
+    .argument("-v")
+    .create(&[Path::File("a.txt")])?
+    .operations(&[Operation {
+      source: "a.txt",
+      destination: Some("b.txt"),
+    }])
+    .exists(&["b.txt"])
+    .expected_stderr(
+      "
+      stage 1 of 1:
+        `a.txt` -> `b.txt`
+      ",
+    )
+    .expected_stdout(
+      "
+      a.txt -> b.txt
+      1 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn traces_checks_with_very_verbose() -> Result {
+  Test::new()?
+    .argument("-vv")
+    .argument("--force")
+    .create(&[
+      Path::File("a.txt"),
+      Path::File("b.txt"),
+      Path::File("c.txt"),
+    ])?
+    .operations(&[
+      Operation {
+        source: "a.txt",
+        destination: Some("c.txt"),
+      },
+      Operation {
+        source: "b.txt",
+        destination: Some("b.txt"),
+      },
+    ])
+    .exists(&["b.txt", "c.txt"])
+    .expected_stderr(
+      "
+      check: dropped `b.txt`, it is unchanged
+      check: `a.txt` -> `c.txt`: exists (allowed by --force)
+      stage 1 of 1:
+        `a.txt` -> `c.txt`
+      ",
+    )
+    .expected_stdout(
+      "
+      a.txt -> c.txt
+      1 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[test]
+fn suppresses_summary_with_quiet() -> Result {
+  Test::new()?
+    .argument("--quiet")
+    .create(&[Path::File("a.txt")])?
+    .operations(&[Operation {
+      source: "a.txt",
+      destination: Some("b.txt"),
+    }])
+    .exists(&["b.txt"])
+    .expected_stdout("a.txt -> b.txt\n")
+    .run()
+}
+
+#[cfg(unix)]
+#[test]
+fn resumes_runs_that_fail_between_resolve_stages() -> Result {
+  let tempdir = TempDir::new()?;
+
+  for name in ["a.txt", "b.txt"] {
+    fs::write(tempdir.path().join(name), name)?;
+  }
+
+  fs::create_dir(tempdir.path().join("sub"))?;
+
+  let output = Test::edmv(&tempdir)
+    .args(["a.txt", "b.txt", "--resolve", "--post-hook", "rm -rf sub"])
+    .arg("--editor")
+    .arg(Test::editor(&tempdir, "x.txt\\nsub/y.txt")?)
+    .output()?;
+
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(fs::read_to_string(tempdir.path().join("x.txt"))?, "a.txt");
+  assert!(!tempdir.path().join("b.txt").exists());
+
+  fs::create_dir(tempdir.path().join("sub"))?;
+
+  let output = Test::edmv(&tempdir).arg("resume").output()?;
+
+  assert!(output.status.success());
+  assert_eq!(
+    fs::read_to_string(tempdir.path().join("sub/y.txt"))?,
+    "b.txt"
+  );
+  assert_eq!(fs::read_dir(tempdir.path().join(".state/edmv"))?.count(), 0);
+
+  Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn runs_beside_journals_of_failed_runs() -> Result {
+  let tempdir = TempDir::new()?;
+
+  fs::create_dir(tempdir.path().join("other"))?;
+  fs::create_dir(tempdir.path().join("sub"))?;
+
+  for path in ["a.txt", "b.txt", "other/q.txt"] {
+    File::create(tempdir.path().join(path))?;
+  }
+
+  let output = Test::edmv(&tempdir)
+    .args(["a.txt", "b.txt", "--post-hook", "rm -rf sub"])
+    .arg("--editor")
+    .arg(Test::editor(&tempdir, "x.txt\\nsub/y.txt")?)
+    .output()?;
+
+  assert_eq!(output.status.code(), Some(1));
+
+  let stderr = str::from_utf8(&output.stderr)?;
+
+  assert!(stderr.starts_with("error: Failed to rename `"));
+  assert!(stderr.contains(&format!(
+    "\nnote: The rest of the run is recorded in journal `{}",
+    tempdir.path().join(".state/edmv/journal-").display()
+  )));
+  assert!(stderr.contains("finish it with `edmv resume`"));
+
+  let output = Test::edmv(&tempdir)
+    .args(["other/q.txt", "--editor"])
+    .arg(Test::editor(&tempdir, "other/r.txt")?)
+    .output()?;
+
+  assert!(output.status.success());
+  assert!(tempdir.path().join("other/r.txt").exists());
+
+  let output = Test::edmv(&tempdir)
+    .args(["b.txt", "--editor", "true"])
+    .output()?;
+
+  assert_eq!(output.status.code(), Some(18));
+
+  Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn runs_alongside_live_runs() -> Result {
+  let hook = format!(
+    "{} two/c.txt --editor true",
+    executable_path(env!("CARGO_PKG_NAME")).display()
+  );
+
+  Test::new()?
+    .argument("--post-hook")
+    .argument(&hook)
+    .create(&[
+      Path::Directory("one"),
+      Path::File("one/a.txt"),
+      Path::Directory("two"),
+      Path::File("two/c.txt"),
+    ])?
+    .operations(&[Operation {
+      source: "one/a.txt",
+      destination: Some("one/b.txt"),
+    }])
+    .exists(&["one", "one/b.txt", "two", "two/c.txt"])
+    .expected_stdout(
+      "
+      one/a.txt -> one/b.txt
+      0 path(s) changed
+      1 path(s) changed
+      ",
+    )
+    .run()
+}
+
+#[cfg(unix)]
+#[test]
+fn resumes_interrupted_runs() -> Result {
+  let tempdir = TempDir::new()?;
+
+  for name in ["a.txt", "b.txt"] {
+    fs::write(tempdir.path().join(name), name)?;
+  }
+
+  let output = Test::edmv(&tempdir)
+    .args(["a.txt", "b.txt", "--post-hook", "kill -9 $PPID", "--editor"])
+    .arg(Test::editor(&tempdir, "c.txt\\nd.txt")?)
+    .output()?;
+
+  assert_eq!(output.status.code(), None);
+  assert!(tempdir.path().join("b.txt").exists());
+  assert!(tempdir.path().join("c.txt").exists());
+
+  let output = Test::edmv(&tempdir)
+    .args(["b.txt", "--editor", "true"])
+    .output()?;
+
+  assert_eq!(output.status.code(), Some(18));
+  assert!(str::from_utf8(&output.stderr)?
+    .contains("an earlier run touching the same paths was interrupted"));
+
+  let output = Test::edmv(&tempdir).arg("resume").output()?;
+
+  assert!(output.status.success());
+  assert!(str::from_utf8(&output.stdout)?.ends_with(&format!(
+    "b.txt -> {}\n1 step(s) resumed\n",
+    tempdir.path().join("d.txt").display()
+  )));
+
+  assert!(!tempdir.path().join("b.txt").exists());
+  assert_eq!(fs::read_to_string(tempdir.path().join("d.txt"))?, "b.txt");
+  assert_eq!(fs::read_dir(tempdir.path().join(".state/edmv"))?.count(), 0);
+
+  let output = Test::edmv(&tempdir).arg("resume").output()?;
+
+  assert_eq!(output.status.code(), Some(18));
+
+  Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn gives_error_for_locked_directories() -> Result {
+  let tempdir = TempDir::new()?;
+
+  File::create(tempdir.path().join("a.txt"))?;
+
+  let lock = tempdir.path().join(".edmv.lock");
+
+  fs::write(&lock, "4242")?;
+
+  let file = File::open(&lock)?;
+
+  file.lock()?;
+
+  let output = Test::edmv(&tempdir)
+    .args(["a.txt", "--editor"])
+    .arg(Test::editor(&tempdir, "b.txt")?)
+    .output()?;
+
+  assert_eq!(output.status.code(), Some(19));
+
+  assert_eq!(
+    str::from_utf8(&output.stderr)?,
+    format!(
+      "error: Directory `{}` is locked by another edmv run (PID 4242), pass --wait to wait for it\n",
+      tempdir.path().display()
+    )
+  );
+
+  assert!(tempdir.path().join("a.txt").exists());
+
+  Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn removes_lock_files_from_renamed_directories() -> Result {
+  let tempdir = Test::new()?
+    .create(&[Path::Directory("dir"), Path::File("dir/a.txt")])?
+    .operations(&[
+      Operation {
+        source: "dir/a.txt",
+        destination: Some("dir/b.txt"),
+      },
+      Operation {
+        source: "dir",
+        destination: Some("newdir"),
+      },
+    ])
+    .exists(&["newdir", "newdir/b.txt"])
+    .expected_stdout(
+      "
+      dir/a.txt -> dir/b.txt
+      dir -> newdir
+      2 path(s) changed
+      ",
+    )
+    .run_and_return_tempdir()?;
+
+  assert!(!tempdir.path().join("newdir/.edmv.lock").exists());
+  assert!(!tempdir.path().join(".edmv.lock").exists());
+
+  Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn gives_error_for_locks_above_or_inside_renamed_paths() -> Result {
+  for (locked, source, destination) in
+    [("", "sub/a.txt", "sub/b.txt"), ("proj", "proj", "renamed")]
+  {
+    let tempdir = TempDir::new()?;
+
+    fs::create_dir(tempdir.path().join("sub"))?;
+    fs::create_dir(tempdir.path().join("proj"))?;
+    File::create(tempdir.path().join("sub/a.txt"))?;
+
+    let directory = tempdir.path().join(locked);
+
+    fs::write(directory.join(".edmv.lock"), "4242")?;
+
+    let file = File::open(directory.join(".edmv.lock"))?;
+
+    file.lock()?;
+
+    let output = Test::edmv(&tempdir)
+      .args([source, "--editor"])
+      .arg(Test::editor(&tempdir, destination)?)
+      .output()?;
+
+    assert_eq!(output.status.code(), Some(19));
+
+    assert_eq!(
+      str::from_utf8(&output.stderr)?,
+      format!(
+        "error: Directory `{}` is locked by another edmv run (PID 4242), pass --wait to wait for it\n",
+        directory.components().collect::<PathBuf>().display()
+      )
+    );
+
+    assert!(tempdir.path().join(source).exists());
+  }
+
+  Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn gives_error_for_renaming_directories_locked_beneath() -> Result {
+  let tempdir = TempDir::new()?;
+
+  fs::create_dir_all(tempdir.path().join("a/b"))?;
+  File::create(tempdir.path().join("a/b/c.txt"))?;
+
+  let slow = tempdir.path().join("slow.sh");
+
+  fs::write(
+    &slow,
+    "#!/bin/bash\ntouch editing\nwhile [ ! -e go ]; do sleep 0.01; done\necho a/b/d.txt > \"$1\"",
+  )?;
+
+  fs::set_permissions(&slow, Permissions::from_mode(0o755))?;
+
+  let first = Test::edmv(&tempdir)
+    .args(["a/b/c.txt", "--editor"])
+    .arg(&slow)
+    .stdout(Stdio::piped())
+    .spawn()?;
+
+  while !tempdir.path().join("editing").exists() {
+    thread::sleep(Duration::from_millis(10));
+  }
+
+  let second = Test::edmv(&tempdir)
+    .args(["a", "--editor"])
+    .arg(Test::editor(&tempdir, "z")?)
+    .output()?;
+
+  let pid = first.id();
+
+  File::create(tempdir.path().join("go"))?;
+
+  let first = first.wait_with_output()?;
+
+  assert_eq!(second.status.code(), Some(19));
+
+  assert_eq!(
+    str::from_utf8(&second.stderr)?,
+    format!(
+      "error: Directory `{}` is locked by another edmv run (PID {}), pass --wait to wait for it\n",
+      tempdir.path().join("a/b").display(),
+      pid
+    )
+  );
+
+  assert!(first.status.success());
+  assert_eq!(
+    str::from_utf8(&first.stdout)?,
+    "a/b/c.txt -> a/b/d.txt\n1 path(s) changed\n"
+  );
+
+  assert!(tempdir.path().join("a/b/d.txt").exists());
+  assert!(!tempdir.path().join("z").exists());
+
+  for directory in ["", "a", "a/b"] {
+    assert!(!tempdir.path().join(directory).join(".edmv.lock").exists());
+  }
+
+  Ok(())
+}
@@ -0,0 +1,217 @@
+use {
+  edmv::{Error, Event, Filesystem, Kind, Memory, Plan, Real},
+  pretty_assertions::assert_eq,
+  std::{ffi::OsString, fs, io::ErrorKind, path::PathBuf},
+  tempfile::TempDir,
+};
+
+type Result<T = (), E = Box<dyn std::error::Error>> = std::result::Result<T, E>;
+
+fn memory(files: &[&str], directories: &[&str]) -> Result<Memory<'static>> {
+  let mut memory = Memory::new();
+
+  for directory in directories {
+    memory.create_dir_all(&root().join(directory))?;
+  }
+
+  for file in files {
+    memory.create_file(&root().join(file))?;
+  }
+
+  Ok(memory)
+}
+
+fn plan(pairs: &[(&str, &str)]) -> Plan {
+  Plan::new(
+    pairs
+      .iter()
+      .map(|(source, destination)| {
+        (root().join(source), root().join(destination))
+      })
+      .collect(),
+  )
+}
+
+fn root() -> PathBuf {
+  PathBuf::from("/edmv")
+}
+
+#[test]
+fn renames_files_in_memory() -> Result {
+  let mut memory = memory(&["a.txt", "b.txt"], &[])?;
+
+  let plan = plan(&[("a.txt", "c.txt"), ("b.txt", "d.txt")]);
+
+  plan.validate(&memory, false, false)?;
+
+  let mut renamed = Vec::new();
+
+  let count = plan.execute(&mut memory, false, |event| {
+    if let Event::Renamed {
+      destination,
+      source,
+    } = event
+    {
+      renamed.push((source.to_path_buf(), destination.to_path_buf()));
+    }
+  })?;
+
+  assert_eq!(count, 2);
+
+  assert_eq!(renamed, plan.pairs());
+
+  for (source, destination) in plan.pairs() {
+    assert!(!memory.exists(source));
+    assert!(memory.is_file(destination));
+  }
+
+  Ok(())
+}
+
+#[test]
+fn resolves_cycles_in_memory() -> Result {
+  let mut memory = memory(&["a.txt", "b.txt", "c.txt"], &[])?;
+
+  let plan =
+    plan(&[("a.txt", "b.txt"), ("b.txt", "c.txt"), ("c.txt", "a.txt")]);
+
+  plan.validate(&memory, true, true)?;
+
+  assert_eq!(plan.execute(&mut memory, true, |_| {})?, 3);
+
+  assert_eq!(
+    memory.read_dir(&root())?,
+    ["a.txt", "b.txt", "c.txt"]
+      .iter()
+      .map(OsString::from)
+      .collect::<Vec<_>>()
+  );
+
+  Ok(())
+}
+
+#[test]
+fn moves_directory_contents_in_memory() -> Result {
+  let mut memory = memory(&["a/x.txt", "a/y/z.txt"], &["b"])?;
+
+  plan(&[("a", "c")]).execute(&mut memory, false, |_| {})?;
+
+  assert!(!memory.exists(&root().join("a")));
+  assert!(memory.is_file(&root().join("c/x.txt")));
+  assert!(memory.is_file(&root().join("c/y/z.txt")));
+
+  plan(&[("c", "b")]).execute(&mut memory, false, |_| {})?;
+
+  assert!(memory.is_file(&root().join("b/c/y/z.txt")));
+
+  Ok(())
+}
+
+#[test]
+fn validates_against_memory() -> Result {
+  let memory = memory(&["a.txt", "b.txt", "c.txt"], &["d"])?;
+
+  let error = plan(&[("a.txt", "b.txt"), ("c.txt", "x/c.txt"), ("d", "b.txt")])
+    .validate(&memory, false, false)
+    .unwrap_err();
+
+  let Error::Validation { problems } = error else {
+    panic!("expected validation error, got {error}");
+  };
+
+  assert_eq!(
+    problems.iter().map(Error::code).collect::<Vec<_>>(),
+    [8, 9, 11, 12]
+  );
+
+  Ok(())
+}
+
+#[test]
+fn refuses_to_replace_paths_created_by_earlier_renames() -> Result {
+  let mut memory = memory(&["a.txt", "b.txt"], &[])?;
+
+  let error = plan(&[("a.txt", "c.txt"), ("b.txt", "c.txt")])
+    .execute(&mut memory, false, |_| {})
+    .unwrap_err();
+
+  let Error::Rename { source, .. } = error else {
+    panic!("expected rename error, got {error}");
+  };
+
+  assert_eq!(source.kind(), ErrorKind::AlreadyExists);
+
+  assert!(memory.exists(&root().join("b.txt")));
+
+  Ok(())
+}
+
+#[test]
+fn refuses_to_replace_non_empty_directories() -> Result {
+  let mut memory = memory(&["a/x.txt", "b/y.txt"], &[])?;
+
+  assert_eq!(
+    memory
+      .rename(&root().join("a"), &root().join("b"))
+      .unwrap_err()
+      .kind(),
+    ErrorKind::DirectoryNotEmpty
+  );
+
+  Ok(())
+}
+
+#[test]
+fn creates_temporaries_in_memory() -> Result {
+  let mut memory = Memory::new();
+
+  let file = memory.temporary(Kind::File)?;
+  let directory = memory.temporary(Kind::Directory)?;
+
+  assert!(memory.is_file(&file));
+  assert!(memory.is_dir(&directory));
+  assert_ne!(file, directory);
+
+  Ok(())
+}
+
+#[test]
+fn overlay_does_not_touch_disk() -> Result {
+  let tempdir = TempDir::new()?;
+
+  let path = |name: &str| tempdir.path().join(name);
+
+  fs::write(path("a.txt"), "")?;
+  fs::create_dir(path("b"))?;
+
+  let real = Real::default();
+
+  let mut memory = Memory::overlay(&real);
+
+  let plan =
+    Plan::new(vec![(path("a.txt"), path("c.txt")), (path("b"), path("d"))]);
+
+  plan.validate(&real, false, false)?;
+
+  let mut renames = Vec::new();
+
+  plan.execute(&mut memory, false, |event| {
+    if let Event::Rename { from, to } = event {
+      renames.push((from.to_path_buf(), to.to_path_buf()));
+    }
+  })?;
+
+  assert_eq!(renames, plan.pairs());
+
+  assert!(memory.is_file(&path("c.txt")));
+  assert!(memory.is_dir(&path("d")));
+  assert!(!memory.exists(&path("a.txt")));
+  assert!(!memory.exists(&path("b")));
+
+  assert!(path("a.txt").is_file());
+  assert!(path("b").is_dir());
+  assert!(!path("c.txt").exists());
+  assert!(!path("d").exists());
+
+  Ok(())
+}